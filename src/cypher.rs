@@ -1,52 +1,581 @@
 use num_bigint::BigUint;
+use num_traits::Zero;
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq};
+
+use crate::keys_generator::{
+    keys::{CrtValues, PrivateKey, PublicKey, SecretNumber},
+    validation::random_below,
+};
+
+/// Size of the SHA-256 digest used by OAEP and PSS
+const HASH_SIZE: usize = 32;
+
+/// Size of the random salt used by PSS
+const PSS_SALT_SIZE: usize = HASH_SIZE;
+
+/// Errors that can occur while using the RSA primitives.
+///
+/// This enum is used to represent the different errors that can occur while decrypting data.
+///
+/// # Variants
+/// - **DecryptionFailed** - The data is not a valid ciphertext for this key
+/// - **KeyTooSmall** - The modulus of the key is too small to hold a padded block
+#[derive(Debug, PartialEq)]
+pub enum CypherError {
+    DecryptionFailed,
+    KeyTooSmall,
+}
+
+impl std::fmt::Display for CypherError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CypherError::DecryptionFailed => write!(f, "decryption failed"),
+            CypherError::KeyTooSmall => write!(f, "modulus too small"),
+        }
+    }
+}
+
+impl std::error::Error for CypherError {}
+
+/// Result type for the RSA primitives.
+///
+/// This type is used to represent the result of the RSA primitives.
+pub type CypherResult<T> = std::result::Result<T, CypherError>;
+
+/// Get the size of a modulus
+///
+/// This function will return the number of bytes needed to write the modulus, which is the size of an RSA block
+///
+/// # Arguments
+/// modulus: **&BigUint** - The modulus of the key
+///
+/// # Returns
+/// **usize** - The size of the modulus in bytes
+pub fn modulus_size(modulus: &BigUint) -> usize {
+    modulus.bits().div_ceil(8) as usize
+}
 
 /// Apply the RSA algorithm
 ///
 /// This function will encrypt or decrypt the data given as parameter using the RSA algorithm
 ///
 /// # Arguments
-/// data: **&Vec<u8>** - The data to cypher/decypher<br/>
+/// data: **&[u8]** - The data to cypher/decypher<br/>
 /// exponent: **&BigUint** - The exponent to use for the operation<br/>
 /// modulus: **&BigUint** - The modulus to use for the operation
 ///
 /// # Returns
-/// **Vec<usize>** - The data encrypted/decrypted
-pub fn enigma(data: &Vec<u8>, exponent: &BigUint, modulus: &BigUint) -> Vec<u8> {
-    let mut chunked_data = data.chunks(256);
+/// **Vec<u8>** - The data encrypted/decrypted
+pub fn enigma(data: &[u8], exponent: &BigUint, modulus: &BigUint) -> Vec<u8> {
+    let mut chunked_data = data.chunks(modulus_size(modulus));
     let mut current_chunk = chunked_data.next();
     let mut res: Vec<Vec<u8>> = vec![];
 
     while current_chunk.is_some() {
         let m: BigUint = BigUint::from_bytes_be(current_chunk.unwrap());
-        m.modpow(exponent, modulus).to_bytes_be();
-        res.push(m.to_bytes_be());
+        res.push(m.modpow(exponent, modulus).to_bytes_be());
         current_chunk = chunked_data.next();
     }
 
     res.into_iter().flatten().collect()
 }
 
+/// Raise a block to the decryption value
+///
+/// This function will use the Chinese Remainder Theorem (Garner's formula) when the primes are known,
+/// replacing the full size exponentiation by two exponentiations modulo p and q
+///
+/// # Arguments
+/// block: **&BigUint** - The block to raise, lower than the modulus<br/>
+/// private_key: **&PrivateKey** - The private key to use
+///
+/// # Returns
+/// **BigUint** - The block raised to the decryption value
+fn unblinded_private_enigma(block: &BigUint, private_key: &PrivateKey) -> BigUint {
+    let crt: &CrtValues = match private_key.crt() {
+        None => return block.modpow(private_key.decryption_value(), &private_key.modulus()),
+        Some(crt) => crt,
+    };
+    let m1: SecretNumber = SecretNumber::new((block % &crt.p).modpow(&crt.dp, &crt.p));
+    let m2: SecretNumber = SecretNumber::new((block % &crt.q).modpow(&crt.dq, &crt.q));
+    let h: SecretNumber =
+        SecretNumber::new((&crt.qinv * (&*m1 + &crt.p - (&*m2 % &crt.p))) % &crt.p);
+
+    &*m2 + &*h * &crt.q
+}
+
+/// Apply the RSA algorithm with a private key
+///
+/// This function will raise a single block to the decryption value.
+/// `modpow` does not run in constant time, so when the public exponent is known the block is blinded first:
+/// it is multiplied by r^e for a fresh random r, and the result by r^-1, so the timing of the exponentiation
+/// does not depend on the block chosen by the peer.
+///
+/// # Arguments
+/// block: **&BigUint** - The block to decrypt or sign, lower than the modulus<br/>
+/// private_key: **&PrivateKey** - The private key to use
+///
+/// # Returns
+/// **BigUint** - The block raised to the decryption value
+pub fn private_enigma(block: &BigUint, private_key: &PrivateKey) -> BigUint {
+    let modulus: BigUint = private_key.modulus();
+    let encryption_value: BigUint = match private_key.encryption_value() {
+        None => return unblinded_private_enigma(block, private_key),
+        Some(encryption_value) => encryption_value,
+    };
+
+    loop {
+        let blinding: SecretNumber = SecretNumber::new(random_below(&modulus));
+        // r must be invertible, which only fails if it shares a prime with the modulus
+        let unblinding: SecretNumber = match blinding.modinv(&modulus) {
+            Some(unblinding) if !blinding.is_zero() => SecretNumber::new(unblinding),
+            _ => continue,
+        };
+        let blinded: BigUint = (block * blinding.modpow(&encryption_value, &modulus)) % &modulus;
+        let result: SecretNumber =
+            SecretNumber::new(unblinded_private_enigma(&blinded, private_key));
+
+        return (&*result * &*unblinding) % &modulus;
+    }
+}
+
+/// Write a number on a fixed number of bytes
+///
+/// This function will left pad the big endian bytes with zeros so that leading zeros are not lost
+///
+/// # Arguments
+/// data: **Vec<u8>** - The big endian bytes of the number<br/>
+/// size: **usize** - The number of bytes expected
+///
+/// # Returns
+/// **Vec<u8>** - The number written on `size` bytes
+fn left_pad(data: Vec<u8>, size: usize) -> Vec<u8> {
+    let mut res: Vec<u8> = vec![0; size.saturating_sub(data.len())];
+
+    res.extend(data);
+    res
+}
+
+/// Mask generation function
+///
+/// This function will implement MGF1 with SHA-256 as described in RFC 8017
+///
+/// # Arguments
+/// seed: **&[u8]** - The seed of the mask<br/>
+/// size: **usize** - The size of the mask
+///
+/// # Returns
+/// **Vec<u8>** - The mask generated
+fn mgf1(seed: &[u8], size: usize) -> Vec<u8> {
+    let mut mask: Vec<u8> = Vec::with_capacity(size + HASH_SIZE);
+    let mut counter: u32 = 0;
+
+    while mask.len() < size {
+        let mut hasher: Sha256 = Sha256::new();
+        hasher.update(seed);
+        hasher.update(counter.to_be_bytes());
+        mask.extend_from_slice(&hasher.finalize());
+        counter += 1;
+    }
+    mask.truncate(size);
+    mask
+}
+
+/// Get the biggest message that fits in an OAEP block
+///
+/// # Arguments
+/// modulus: **&BigUint** - The modulus of the key
+///
+/// # Returns
+/// **CypherResult<usize>** - The maximum number of plain bytes per block or an error if no byte fits
+fn oaep_max_message_size(modulus: &BigUint) -> CypherResult<usize> {
+    modulus_size(modulus)
+        .checked_sub(2 * HASH_SIZE + 2)
+        .filter(|size: &usize| *size > 0)
+        .ok_or(CypherError::KeyTooSmall)
+}
+
+/// Encode a single block with OAEP
+///
+/// # Arguments
+/// message: **&[u8]** - The message to encode, at most `oaep_max_message_size` bytes<br/>
+/// size: **usize** - The size of the modulus in bytes
+///
+/// # Returns
+/// **Vec<u8>** - The encoded block of `size` bytes
+fn oaep_encode(message: &[u8], size: usize) -> Vec<u8> {
+    let label_hash = Sha256::digest([]);
+    let mut seed: [u8; HASH_SIZE] = [0; HASH_SIZE];
+    let mut data_block: Vec<u8> = Vec::with_capacity(size - HASH_SIZE - 1);
+
+    rand::thread_rng().fill_bytes(&mut seed);
+    data_block.extend_from_slice(&label_hash);
+    data_block.resize(size - message.len() - HASH_SIZE - 2, 0);
+    data_block.push(1);
+    data_block.extend_from_slice(message);
+    for (byte, mask) in data_block.iter_mut().zip(mgf1(&seed, size - HASH_SIZE - 1)) {
+        *byte ^= mask;
+    }
+    for (byte, mask) in seed.iter_mut().zip(mgf1(&data_block, HASH_SIZE)) {
+        *byte ^= mask;
+    }
+    let mut block: Vec<u8> = Vec::with_capacity(size);
+    block.push(0);
+    block.extend_from_slice(&seed);
+    block.extend_from_slice(&data_block);
+    block
+}
+
+/// Decode a single OAEP block
+///
+/// # Arguments
+/// block: **&[u8]** - The decrypted block
+///
+/// # Returns
+/// **CypherResult<Vec<u8>>** - The message or an error if the block is not a valid OAEP encoding
+fn oaep_decode(block: &[u8]) -> CypherResult<Vec<u8>> {
+    let label_hash = Sha256::digest([]);
+    let mut seed: Vec<u8> = block[1..HASH_SIZE + 1].to_vec();
+    let mut data_block: Vec<u8> = block[HASH_SIZE + 1..].to_vec();
+
+    for (byte, mask) in seed.iter_mut().zip(mgf1(&data_block, HASH_SIZE)) {
+        *byte ^= mask;
+    }
+    for (byte, mask) in data_block
+        .iter_mut()
+        .zip(mgf1(&seed, block.len() - HASH_SIZE - 1))
+    {
+        *byte ^= mask;
+    }
+    // Every byte is looked at whatever the content of the block, so that the time taken does not tell
+    // the peer which check failed (Manger's attack)
+    let mut looking: Choice = Choice::from(1);
+    let mut valid_padding: Choice = Choice::from(1);
+    let mut separator: u32 = 0;
+
+    for (index, byte) in data_block[HASH_SIZE..].iter().enumerate() {
+        let is_zero: Choice = byte.ct_eq(&0);
+        let is_one: Choice = byte.ct_eq(&1);
+
+        separator.conditional_assign(&(index as u32), looking & is_one);
+        valid_padding &= !looking | is_zero | is_one;
+        looking &= is_zero;
+    }
+    let valid: Choice = block[0].ct_eq(&0)
+        & data_block[..HASH_SIZE].ct_eq(&label_hash[..])
+        & !looking
+        & valid_padding;
+    if !bool::from(valid) {
+        return Err(CypherError::DecryptionFailed);
+    }
+    Ok(data_block[HASH_SIZE + separator as usize + 1..].to_vec())
+}
+
+/// Encrypt data with RSA-OAEP
+///
+/// This function will split the data in blocks fitting the modulus, pad each of them with OAEP (SHA-256) and encrypt them
+///
+/// # Arguments
+/// data: **&[u8]** - The data to encrypt<br/>
+/// public_key: **&PublicKey** - The public key of the recipient
+///
+/// # Returns
+/// **CypherResult<Vec<u8>>** - The encrypted blocks, each one as long as the modulus,
+/// or an error if the modulus is too small for OAEP
+pub fn oaep_encrypt(data: &[u8], public_key: &PublicKey) -> CypherResult<Vec<u8>> {
+    let modulus: BigUint = public_key.modulus();
+    let exponent: BigUint = public_key.encryption_value();
+    let size: usize = modulus_size(&modulus);
+    let max_message_size: usize = oaep_max_message_size(&modulus)?;
+    let mut res: Vec<u8> = vec![];
+
+    for chunk in data.chunks(max_message_size) {
+        let block: Vec<u8> = oaep_encode(chunk, size);
+        res.extend(left_pad(enigma(&block, &exponent, &modulus), size));
+    }
+    if data.is_empty() {
+        let block: Vec<u8> = oaep_encode(&[], size);
+        res.extend(left_pad(enigma(&block, &exponent, &modulus), size));
+    }
+    Ok(res)
+}
+
+/// Decrypt data encrypted with RSA-OAEP
+///
+/// This function will decrypt each block and remove its OAEP padding
+///
+/// # Arguments
+/// data: **&[u8]** - The encrypted blocks<br/>
+/// private_key: **&PrivateKey** - The private key matching the public key used to encrypt
+///
+/// # Returns
+/// **CypherResult<Vec<u8>>** - The plain data or an error if any block is invalid
+pub fn oaep_decrypt(data: &[u8], private_key: &PrivateKey) -> CypherResult<Vec<u8>> {
+    let modulus: BigUint = private_key.modulus();
+    let size: usize = modulus_size(&modulus);
+    let mut res: Vec<u8> = vec![];
+
+    if size < 2 * HASH_SIZE + 2 || data.is_empty() || !data.len().is_multiple_of(size) {
+        return Err(CypherError::DecryptionFailed);
+    }
+    for chunk in data.chunks(size) {
+        if BigUint::from_bytes_be(chunk) >= modulus {
+            return Err(CypherError::DecryptionFailed);
+        }
+        let block: BigUint = private_enigma(&BigUint::from_bytes_be(chunk), private_key);
+        res.extend(oaep_decode(&left_pad(block.to_bytes_be(), size))?);
+    }
+    Ok(res)
+}
+
+/// Encode a message digest with PSS
+///
+/// # Arguments
+/// message_hash: **&[u8]** - The SHA-256 digest of the message<br/>
+/// salt: **&[u8]** - The random salt<br/>
+/// em_bits: **usize** - The number of bits of the encoded message, one less than the modulus
+///
+/// # Returns
+/// **CypherResult<Vec<u8>>** - The encoded message or an error if the salt and the hash do not fit in it
+fn pss_encode(message_hash: &[u8], salt: &[u8], em_bits: usize) -> CypherResult<Vec<u8>> {
+    let em_size: usize = em_bits.div_ceil(8);
+    let padding_size: usize = em_size
+        .checked_sub(salt.len() + HASH_SIZE + 2)
+        .ok_or(CypherError::KeyTooSmall)?;
+    let mut hasher: Sha256 = Sha256::new();
+    hasher.update([0; 8]);
+    hasher.update(message_hash);
+    hasher.update(salt);
+    let hash = hasher.finalize();
+    let mut data_block: Vec<u8> = vec![0; padding_size];
+
+    data_block.push(1);
+    data_block.extend_from_slice(salt);
+    for (byte, mask) in data_block
+        .iter_mut()
+        .zip(mgf1(&hash, em_size - HASH_SIZE - 1))
+    {
+        *byte ^= mask;
+    }
+    data_block[0] &= 0xff >> (8 * em_size - em_bits);
+    data_block.extend_from_slice(&hash);
+    data_block.push(0xbc);
+    Ok(data_block)
+}
+
+/// Sign a message with RSA-PSS
+///
+/// This function will sign the SHA-256 digest of the message with PSS padding and a random salt
+///
+/// # Arguments
+/// message: **&[u8]** - The message to sign<br/>
+/// private_key: **&PrivateKey** - The private key of the signer
+///
+/// # Returns
+/// **CypherResult<Vec<u8>>** - The signature, as long as the modulus, or an error if the modulus is too small for PSS
+pub fn pss_sign(message: &[u8], private_key: &PrivateKey) -> CypherResult<Vec<u8>> {
+    let modulus: BigUint = private_key.modulus();
+    let mut salt: [u8; PSS_SALT_SIZE] = [0; PSS_SALT_SIZE];
+
+    rand::thread_rng().fill_bytes(&mut salt);
+    let encoded: Vec<u8> = pss_encode(
+        &Sha256::digest(message),
+        &salt,
+        (modulus.bits() as usize).saturating_sub(1),
+    )?;
+    Ok(left_pad(
+        private_enigma(&BigUint::from_bytes_be(&encoded), private_key).to_bytes_be(),
+        modulus_size(&modulus),
+    ))
+}
+
+/// Verify an RSA-PSS signature
+///
+/// This function will check that the signature was produced by the private key matching the public key
+///
+/// # Arguments
+/// message: **&[u8]** - The message that was signed<br/>
+/// signature: **&[u8]** - The signature to check<br/>
+/// public_key: **&PublicKey** - The public key of the signer
+///
+/// # Returns
+/// **bool** - True if the signature is valid, false otherwise
+pub fn pss_verify(message: &[u8], signature: &[u8], public_key: &PublicKey) -> bool {
+    let modulus: BigUint = public_key.modulus();
+    let em_bits: usize = (modulus.bits() as usize).saturating_sub(1);
+    let em_size: usize = em_bits.div_ceil(8);
+
+    if signature.len() != modulus_size(&modulus)
+        || em_size < HASH_SIZE + PSS_SALT_SIZE + 2
+        || BigUint::from_bytes_be(signature) >= modulus
+    {
+        return false;
+    }
+    let encoded: Vec<u8> = enigma(signature, &public_key.encryption_value(), &modulus);
+    if encoded.len() > em_size {
+        return false;
+    }
+    let encoded: Vec<u8> = left_pad(encoded, em_size);
+    let mut data_block: Vec<u8> = encoded[..em_size - HASH_SIZE - 1].to_vec();
+    let hash: &[u8] = &encoded[em_size - HASH_SIZE - 1..em_size - 1];
+
+    if encoded[em_size - 1] != 0xbc || data_block[0] & !(0xff >> (8 * em_size - em_bits)) != 0 {
+        return false;
+    }
+    for (byte, mask) in data_block
+        .iter_mut()
+        .zip(mgf1(hash, em_size - HASH_SIZE - 1))
+    {
+        *byte ^= mask;
+    }
+    data_block[0] &= 0xff >> (8 * em_size - em_bits);
+    let salt_start: usize = data_block.len() - PSS_SALT_SIZE;
+    if data_block[..salt_start - 1].iter().any(|&byte| byte != 0) || data_block[salt_start - 1] != 1
+    {
+        return false;
+    }
+    pss_encode(&Sha256::digest(message), &data_block[salt_start..], em_bits) == Ok(encoded)
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::keys_generator::keys::generate_keys;
+    use crate::keys_generator::keys::{generate_keys, KeyOptions};
 
     use super::*;
 
     #[test]
     fn test_enigma() {
-        let double_keys = generate_keys();
+        let double_keys = generate_keys(&KeyOptions::default());
         let data = "Hello";
         let c: Vec<u8> = enigma(
-            &data.as_bytes().to_vec(),
+            data.as_bytes(),
             &double_keys.0.encryption_value(),
             &double_keys.0.modulus(),
         );
         let p = enigma(
             &c,
-            &double_keys.1.decryption_value(),
+            double_keys.1.decryption_value(),
             &double_keys.1.modulus(),
         );
         println!("{:?} and {:?}", p, data.as_bytes().to_vec());
         assert_eq!(data.as_bytes().to_vec(), p);
     }
+
+    #[test]
+    fn test_private_enigma() {
+        let (_, private_key) = generate_keys(&KeyOptions::default());
+        let plain_key: PrivateKey =
+            PrivateKey::new(private_key.decryption_value(), &private_key.modulus());
+        let modulus: BigUint = private_key.modulus();
+
+        assert!(private_key.crt().is_some());
+        for block in [
+            BigUint::from(0u32),
+            BigUint::from(42u32),
+            &modulus - BigUint::from(1u32),
+            BigUint::from_bytes_be(&[0xa5; 200]),
+        ] {
+            let expected: BigUint = unblinded_private_enigma(&block, &private_key);
+
+            // Blinded with CRT, then without the public exponent nor the primes
+            assert_eq!(private_enigma(&block, &private_key), expected);
+            assert_eq!(private_enigma(&block, &plain_key), expected);
+        }
+    }
+
+    /// Run with `cargo test --release -- --ignored --nocapture bench_private_enigma`
+    #[test]
+    #[ignore]
+    fn bench_private_enigma() {
+        const ROUNDS: u32 = 50;
+        let (public_key, private_key) = generate_keys(&KeyOptions::default());
+        let plain_key: PrivateKey =
+            PrivateKey::new(private_key.decryption_value(), &private_key.modulus());
+        let block: BigUint =
+            BigUint::from_bytes_be(&oaep_encrypt(b"benchmark", &public_key).unwrap());
+
+        for (name, key) in [("full exponent", &plain_key), ("CRT", &private_key)] {
+            let start: std::time::Instant = std::time::Instant::now();
+            for _ in 0..ROUNDS {
+                private_enigma(&block, key);
+            }
+            println!(
+                "{}: {:?} per {} bits operation",
+                name,
+                start.elapsed() / ROUNDS,
+                private_key.modulus().bits()
+            );
+        }
+    }
+
+    #[test]
+    fn test_oaep() {
+        let (public_key, private_key) = generate_keys(&KeyOptions::default());
+        let size: usize = modulus_size(&public_key.modulus());
+        let data: Vec<u8> = (0..1000).map(|x| (x % 7) as u8).collect();
+
+        let c: Vec<u8> = oaep_encrypt(&data, &public_key).unwrap();
+        assert_eq!(c.len() % size, 0);
+        assert_ne!(c, oaep_encrypt(&data, &public_key).unwrap());
+        assert_eq!(oaep_decrypt(&c, &private_key), Ok(data));
+
+        let leading_zeros: Vec<u8> = vec![0, 0, 0, 1];
+        let c: Vec<u8> = oaep_encrypt(&leading_zeros, &public_key).unwrap();
+        assert_eq!(oaep_decrypt(&c, &private_key), Ok(leading_zeros));
+
+        let mut tampered: Vec<u8> = c.clone();
+        tampered[size / 2] ^= 1;
+        assert_eq!(
+            oaep_decrypt(&tampered, &private_key),
+            Err(CypherError::DecryptionFailed)
+        );
+        assert_eq!(
+            oaep_decrypt(&c[1..], &private_key),
+            Err(CypherError::DecryptionFailed)
+        );
+    }
+
+    #[test]
+    fn test_oaep_small_modulus() {
+        let exponent: BigUint = BigUint::from(65537u32);
+
+        // Under 66 bytes the padding alone does not fit, at 66 bytes no message byte does
+        for size in [1, 65, 66] {
+            let modulus: BigUint = BigUint::from_bytes_be(&vec![0xff; size]);
+            assert_eq!(
+                oaep_encrypt(b"data", &PublicKey::new(&exponent, &modulus)),
+                Err(CypherError::KeyTooSmall)
+            );
+        }
+    }
+
+    #[test]
+    fn test_pss() {
+        let (public_key, private_key) = generate_keys(&KeyOptions::default());
+        let (other_public_key, _) = generate_keys(&KeyOptions::default());
+        let signature: Vec<u8> = pss_sign(b"transcript", &private_key).unwrap();
+
+        assert!(pss_verify(b"transcript", &signature, &public_key));
+        assert_ne!(signature, pss_sign(b"transcript", &private_key).unwrap());
+        assert!(!pss_verify(b"transcripT", &signature, &public_key));
+        assert!(!pss_verify(b"transcript", &signature, &other_public_key));
+        let mut tampered: Vec<u8> = signature.clone();
+        tampered[10] ^= 1;
+        assert!(!pss_verify(b"transcript", &tampered, &public_key));
+        assert!(!pss_verify(b"transcript", &signature[1..], &public_key));
+    }
+
+    #[test]
+    fn test_pss_small_modulus() {
+        // 65 bytes of encoded message, one short of the salt, the hash and their two marker bytes
+        let modulus: BigUint = BigUint::from_bytes_be(&[0xff; 66]) >> 7u32;
+        let private_key: PrivateKey = PrivateKey::new(&BigUint::from(3u32), &modulus);
+
+        assert_eq!(
+            pss_sign(b"transcript", &private_key),
+            Err(CypherError::KeyTooSmall)
+        );
+    }
 }
@@ -0,0 +1,703 @@
+//! PEM and DER encodings of the RSA keys
+//!
+//! Private keys are written as PKCS#1 `RSAPrivateKey` or PKCS#8 `PrivateKeyInfo`, public keys as
+//! PKCS#1 `RSAPublicKey` or X.509 `SubjectPublicKeyInfo`, which are the formats produced by openssl.
+
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use base64::{
+    engine::general_purpose::{STANDARD, STANDARD_NO_PAD},
+    Engine,
+};
+use num_bigint::BigUint;
+use num_traits::Zero;
+use sha2::{Digest, Sha256};
+use zeroize::Zeroizing;
+
+use super::{
+    errors::{KeyError, KeyResult, ValidationError},
+    keys::{CrtValues, PrivateKey, PublicKey, SecretNumber},
+    validation::{is_probable_prime, validate_private_key, validate_public_key},
+};
+
+const INTEGER_TAG: u8 = 0x02;
+const BIT_STRING_TAG: u8 = 0x03;
+const OCTET_STRING_TAG: u8 = 0x04;
+const NULL_TAG: u8 = 0x05;
+const OID_TAG: u8 = 0x06;
+const SEQUENCE_TAG: u8 = 0x30;
+
+/// Content of the rsaEncryption object identifier (1.2.840.113549.1.1.1)
+const RSA_ENCRYPTION_OID: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x01];
+
+/// Number of base64 characters per line in a PEM file
+const PEM_LINE_SIZE: usize = 64;
+
+const PKCS1_PRIVATE_LABEL: &str = "RSA PRIVATE KEY";
+const PKCS8_PRIVATE_LABEL: &str = "PRIVATE KEY";
+const PKCS1_PUBLIC_LABEL: &str = "RSA PUBLIC KEY";
+const PKCS8_PUBLIC_LABEL: &str = "PUBLIC KEY";
+
+/// Structure used to encode a key
+///
+/// # Variants
+/// - **Pkcs1** - The RSA specific structure (`RSAPrivateKey` / `RSAPublicKey`)
+/// - **Pkcs8** - The algorithm tagged structure (`PrivateKeyInfo` / `SubjectPublicKeyInfo`)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KeyFormat {
+    Pkcs1,
+    Pkcs8,
+}
+
+/// Encode a DER element
+///
+/// # Arguments
+/// tag: **u8** - The tag of the element<br/>
+/// content: **&[u8]** - The encoded content of the element
+///
+/// # Returns
+/// **Vec<u8>** - The element encoded
+fn write_tlv(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut res: Vec<u8> = vec![tag];
+
+    if content.len() < 0x80 {
+        res.push(content.len() as u8);
+    } else {
+        let length: Vec<u8> = BigUint::from(content.len()).to_bytes_be();
+        res.push(0x80 | length.len() as u8);
+        res.extend(length);
+    }
+    res.extend_from_slice(content);
+    res
+}
+
+/// Encode a positive DER integer
+///
+/// # Arguments
+/// value: **&BigUint** - The integer to encode
+///
+/// # Returns
+/// **Vec<u8>** - The integer encoded
+fn write_integer(value: &BigUint) -> Vec<u8> {
+    let mut content: Vec<u8> = value.to_bytes_be();
+
+    if content[0] & 0x80 != 0 {
+        content.insert(0, 0);
+    }
+    write_tlv(INTEGER_TAG, &content)
+}
+
+/// Encode a DER sequence
+///
+/// # Arguments
+/// elements: **&[Vec<u8>]** - The encoded elements of the sequence
+///
+/// # Returns
+/// **Vec<u8>** - The sequence encoded
+fn write_sequence(elements: &[Vec<u8>]) -> Vec<u8> {
+    write_tlv(SEQUENCE_TAG, &elements.concat())
+}
+
+/// Encode the rsaEncryption algorithm identifier
+///
+/// # Returns
+/// **Vec<u8>** - The algorithm identifier encoded
+fn write_algorithm_identifier() -> Vec<u8> {
+    write_sequence(&[
+        write_tlv(OID_TAG, RSA_ENCRYPTION_OID),
+        write_tlv(NULL_TAG, &[]),
+    ])
+}
+
+/// Reader of DER elements
+///
+/// # Fields
+/// - **data** - The bytes that have not been read yet
+struct DerReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> DerReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        DerReader { data }
+    }
+
+    /// Get the tag of the next element without consuming it
+    fn peek_tag(self: &Self) -> Option<u8> {
+        self.data.first().copied()
+    }
+
+    /// Read the next element, which must have the given tag, and return its content
+    fn read(self: &mut Self, tag: u8) -> KeyResult<&'a [u8]> {
+        if self.data.len() < 2 || self.data[0] != tag {
+            return Err(KeyError::InvalidEncoding);
+        }
+        let (length, header): (usize, usize) = match self.data[1] {
+            size if size < 0x80 => (size as usize, 2),
+            size => {
+                let size_length: usize = (size & 0x7f) as usize;
+                if size_length == 0 || size_length > 4 || self.data.len() < 2 + size_length {
+                    return Err(KeyError::InvalidEncoding);
+                }
+                let length: usize = self.data[2..2 + size_length]
+                    .iter()
+                    .fold(0, |acc, &byte| (acc << 8) | byte as usize);
+                (length, 2 + size_length)
+            }
+        };
+        if self.data.len() - header < length {
+            return Err(KeyError::InvalidEncoding);
+        }
+        let content: &'a [u8] = &self.data[header..header + length];
+        self.data = &self.data[header + length..];
+        Ok(content)
+    }
+
+    /// Read the next element as a positive integer
+    fn read_integer(self: &mut Self) -> KeyResult<BigUint> {
+        let content: &[u8] = self.read(INTEGER_TAG)?;
+
+        if content.is_empty() || content[0] & 0x80 != 0 {
+            return Err(KeyError::InvalidEncoding);
+        }
+        Ok(BigUint::from_bytes_be(content))
+    }
+
+    /// Read the next element as a sequence and return a reader over its elements
+    fn read_sequence(self: &mut Self) -> KeyResult<DerReader<'a>> {
+        Ok(DerReader::new(self.read(SEQUENCE_TAG)?))
+    }
+
+    /// Read the next element as an algorithm identifier and check that it is rsaEncryption
+    fn read_algorithm_identifier(self: &mut Self) -> KeyResult<()> {
+        let mut algorithm: DerReader = self.read_sequence()?;
+
+        if algorithm.read(OID_TAG)? != RSA_ENCRYPTION_OID {
+            return Err(KeyError::UnsupportedAlgorithm);
+        }
+        if algorithm.peek_tag() == Some(NULL_TAG) {
+            algorithm.read(NULL_TAG)?;
+        }
+        algorithm.finish()
+    }
+
+    /// Check that every byte has been read
+    fn finish(self: &Self) -> KeyResult<()> {
+        match self.data.is_empty() {
+            true => Ok(()),
+            false => Err(KeyError::InvalidEncoding),
+        }
+    }
+}
+
+/// Encode DER data as PEM
+///
+/// # Arguments
+/// label: **&str** - The label of the PEM block<br/>
+/// der: **&[u8]** - The DER data
+///
+/// # Returns
+/// **String** - The PEM block
+fn encode_pem(label: &str, der: &[u8]) -> String {
+    let encoded: String = STANDARD.encode(der);
+    let mut res: String = format!("-----BEGIN {}-----\n", label);
+
+    for line in encoded.as_bytes().chunks(PEM_LINE_SIZE) {
+        res.push_str(std::str::from_utf8(line).unwrap());
+        res.push('\n');
+    }
+    res.push_str(&format!("-----END {}-----\n", label));
+    res
+}
+
+/// Decode the first PEM block of a text
+///
+/// # Arguments
+/// data: **&str** - The PEM text
+///
+/// # Returns
+/// **KeyResult<(String, Vec<u8>)>** - The label of the block and its DER data
+fn decode_pem(data: &str) -> KeyResult<(String, Vec<u8>)> {
+    let mut lines = data.lines().map(str::trim);
+    let label: String = lines
+        .find_map(|line| line.strip_prefix("-----BEGIN "))
+        .and_then(|line| line.strip_suffix("-----"))
+        .ok_or(KeyError::InvalidEncoding)?
+        .to_string();
+    let footer: String = format!("-----END {}-----", label);
+    let mut encoded: String = String::new();
+
+    for line in lines.by_ref() {
+        if line == footer {
+            let der: Vec<u8> = STANDARD
+                .decode(&encoded)
+                .map_err(|_| KeyError::InvalidEncoding)?;
+            return Ok((label, der));
+        }
+        encoded.push_str(line);
+    }
+    Err(KeyError::InvalidEncoding)
+}
+
+/// Check if a file holds a DER key
+///
+/// # Arguments
+/// path: **&Path** - The path of the key file
+///
+/// # Returns
+/// **bool** - True if the extension of the file is `.der`, false for PEM
+fn is_der_file(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("der"))
+}
+
+/// Get the path of the public key matching a private key file
+///
+/// This function will append `.pub` to the path of the private key
+///
+/// # Arguments
+/// path: **&Path** - The path of the private key file
+///
+/// # Returns
+/// **PathBuf** - The path of the public key file
+pub fn public_key_path(path: &Path) -> PathBuf {
+    let mut public_path: PathBuf = path.to_path_buf();
+
+    public_path.as_mut_os_string().push(".pub");
+    public_path
+}
+
+/// Content of a key file
+///
+/// # Variants
+/// - **Pem** - The file holds PEM text
+/// - **Der** - The file holds raw DER data
+enum KeyFile {
+    Pem(String),
+    Der(Vec<u8>),
+}
+
+/// Read a key file
+///
+/// # Arguments
+/// path: **&Path** - The path of the key file
+///
+/// # Returns
+/// **KeyResult<KeyFile>** - The content of the file
+fn read_key_file(path: &Path) -> KeyResult<KeyFile> {
+    let data: Vec<u8> = fs::read(path)?;
+
+    match String::from_utf8(data) {
+        Ok(text) if text.contains("-----BEGIN ") => Ok(KeyFile::Pem(text)),
+        Ok(text) => Ok(KeyFile::Der(text.into_bytes())),
+        Err(err) => Ok(KeyFile::Der(err.into_bytes())),
+    }
+}
+
+impl PublicKey {
+    /// Get the fingerprint of the public key
+    ///
+    /// This function will hash the `SubjectPublicKeyInfo` DER encoding of the key with SHA-256,
+    /// which is what `openssl pkey -pubin -outform DER | openssl dgst -sha256` computes
+    ///
+    /// # Returns
+    /// **String** - The fingerprint, written `SHA256:` followed by the unpadded base64 digest
+    pub fn fingerprint(self: &Self) -> String {
+        let digest = Sha256::digest(self.to_der(KeyFormat::Pkcs8));
+
+        format!("SHA256:{}", STANDARD_NO_PAD.encode(digest))
+    }
+
+    /// Encode the public key as DER
+    ///
+    /// # Arguments
+    /// format: **KeyFormat** - The structure used to encode the key
+    ///
+    /// # Returns
+    /// **Vec<u8>** - The key encoded
+    pub fn to_der(self: &Self, format: KeyFormat) -> Vec<u8> {
+        let pkcs1: Vec<u8> = write_sequence(&[
+            write_integer(&self.modulus()),
+            write_integer(&self.encryption_value()),
+        ]);
+
+        match format {
+            KeyFormat::Pkcs1 => pkcs1,
+            KeyFormat::Pkcs8 => write_sequence(&[
+                write_algorithm_identifier(),
+                write_tlv(BIT_STRING_TAG, &[&[0], pkcs1.as_slice()].concat()),
+            ]),
+        }
+    }
+
+    /// Decode a DER public key
+    ///
+    /// This function will accept both a PKCS#1 `RSAPublicKey` and a `SubjectPublicKeyInfo`
+    ///
+    /// # Arguments
+    /// data: **&[u8]** - The DER data
+    ///
+    /// # Returns
+    /// **KeyResult<PublicKey>** - The key decoded
+    pub fn from_der(data: &[u8]) -> KeyResult<Self> {
+        let mut reader: DerReader = DerReader::new(data);
+        let mut key: DerReader = reader.read_sequence()?;
+        reader.finish()?;
+
+        if key.peek_tag() == Some(SEQUENCE_TAG) {
+            key.read_algorithm_identifier()?;
+            let bits: &[u8] = key.read(BIT_STRING_TAG)?;
+            key.finish()?;
+            return match bits.split_first() {
+                Some((0, pkcs1)) => PublicKey::from_der(pkcs1),
+                _ => Err(KeyError::InvalidEncoding),
+            };
+        }
+        let modulus: BigUint = key.read_integer()?;
+        let encryption_value: BigUint = key.read_integer()?;
+        key.finish()?;
+        let public_key: PublicKey = PublicKey::new(&encryption_value, &modulus);
+        validate_public_key(&public_key)?;
+        Ok(public_key)
+    }
+
+    /// Encode the public key as PEM
+    ///
+    /// # Arguments
+    /// format: **KeyFormat** - The structure used to encode the key
+    ///
+    /// # Returns
+    /// **String** - The PEM block of the key
+    pub fn to_pem(self: &Self, format: KeyFormat) -> String {
+        let label: &str = match format {
+            KeyFormat::Pkcs1 => PKCS1_PUBLIC_LABEL,
+            KeyFormat::Pkcs8 => PKCS8_PUBLIC_LABEL,
+        };
+        encode_pem(label, &self.to_der(format))
+    }
+
+    /// Decode a PEM public key
+    ///
+    /// # Arguments
+    /// data: **&str** - The PEM text
+    ///
+    /// # Returns
+    /// **KeyResult<PublicKey>** - The key decoded
+    pub fn from_pem(data: &str) -> KeyResult<Self> {
+        match decode_pem(data)? {
+            (label, der) if label == PKCS1_PUBLIC_LABEL || label == PKCS8_PUBLIC_LABEL => {
+                PublicKey::from_der(&der)
+            }
+            _ => Err(KeyError::InvalidEncoding),
+        }
+    }
+
+    /// Save the public key to a file
+    ///
+    /// This function will write the key as DER if the file has a `.der` extension, as PEM otherwise
+    ///
+    /// # Arguments
+    /// path: **&Path** - The path of the key file<br/>
+    /// format: **KeyFormat** - The structure used to encode the key
+    ///
+    /// # Returns
+    /// **KeyResult<()>** - An error if the file could not be written
+    pub fn save(self: &Self, path: &Path, format: KeyFormat) -> KeyResult<()> {
+        match is_der_file(path) {
+            true => fs::write(path, self.to_der(format))?,
+            false => fs::write(path, self.to_pem(format))?,
+        }
+        Ok(())
+    }
+
+    /// Load a public key from a file
+    ///
+    /// This function will read a PEM or DER file in any of the supported formats
+    ///
+    /// # Arguments
+    /// path: **&Path** - The path of the key file
+    ///
+    /// # Returns
+    /// **KeyResult<PublicKey>** - The key loaded
+    pub fn load(path: &Path) -> KeyResult<Self> {
+        match read_key_file(path)? {
+            KeyFile::Pem(text) => PublicKey::from_pem(&text),
+            KeyFile::Der(der) => PublicKey::from_der(&der),
+        }
+    }
+}
+
+impl PrivateKey {
+    /// Encode the private key as DER
+    ///
+    /// # Arguments
+    /// format: **KeyFormat** - The structure used to encode the key
+    ///
+    /// # Returns
+    /// **KeyResult<Vec<u8>>** - The key encoded or an error if the primes or the public exponent are unknown
+    pub fn to_der(self: &Self, format: KeyFormat) -> KeyResult<Vec<u8>> {
+        let encryption_value: BigUint =
+            self.encryption_value().ok_or(KeyError::MissingComponents)?;
+        let crt: &CrtValues = self.crt().ok_or(KeyError::MissingComponents)?;
+        let pkcs1: Vec<u8> = write_sequence(&[
+            write_integer(&BigUint::zero()),
+            write_integer(&self.modulus()),
+            write_integer(&encryption_value),
+            write_integer(self.decryption_value()),
+            write_integer(&crt.p),
+            write_integer(&crt.q),
+            write_integer(&crt.dp),
+            write_integer(&crt.dq),
+            write_integer(&crt.qinv),
+        ]);
+
+        match format {
+            KeyFormat::Pkcs1 => Ok(pkcs1),
+            KeyFormat::Pkcs8 => Ok(write_sequence(&[
+                write_integer(&BigUint::zero()),
+                write_algorithm_identifier(),
+                write_tlv(OCTET_STRING_TAG, &pkcs1),
+            ])),
+        }
+    }
+
+    /// Decode a DER private key
+    ///
+    /// This function will accept both a PKCS#1 `RSAPrivateKey` and a PKCS#8 `PrivateKeyInfo`
+    ///
+    /// # Arguments
+    /// data: **&[u8]** - The DER data
+    ///
+    /// # Returns
+    /// **KeyResult<PrivateKey>** - The key decoded
+    pub fn from_der(data: &[u8]) -> KeyResult<Self> {
+        let mut reader: DerReader = DerReader::new(data);
+        let mut key: DerReader = reader.read_sequence()?;
+        reader.finish()?;
+        let version: BigUint = key.read_integer()?;
+
+        if key.peek_tag() == Some(SEQUENCE_TAG) {
+            key.read_algorithm_identifier()?;
+            return PrivateKey::from_der(key.read(OCTET_STRING_TAG)?);
+        }
+        if !version.is_zero() {
+            return Err(KeyError::InvalidEncoding);
+        }
+        let modulus: BigUint = key.read_integer()?;
+        let encryption_value: BigUint = key.read_integer()?;
+        let decryption_value: SecretNumber = SecretNumber::new(key.read_integer()?);
+        let crt: CrtValues = CrtValues {
+            p: key.read_integer()?,
+            q: key.read_integer()?,
+            dp: key.read_integer()?,
+            dq: key.read_integer()?,
+            qinv: key.read_integer()?,
+        };
+        key.finish()?;
+        if &crt.p * &crt.q != modulus {
+            return Err(ValidationError::ModulusMismatch.into());
+        }
+        // The CRT values divide by p - 1 and q - 1, so the factors are checked before they are used
+        if !is_probable_prime(&crt.p) || !is_probable_prime(&crt.q) {
+            return Err(ValidationError::NotPrime.into());
+        }
+        let private_key: PrivateKey =
+            PrivateKey::from_primes(&encryption_value, &decryption_value, &crt.p, &crt.q);
+        // Wrong CRT values would silently produce wrong signatures
+        if private_key.crt() != Some(&crt) {
+            return Err(ValidationError::InvalidCrtValues.into());
+        }
+        validate_private_key(&private_key)?;
+        Ok(private_key)
+    }
+
+    /// Encode the private key as PEM
+    ///
+    /// # Arguments
+    /// format: **KeyFormat** - The structure used to encode the key
+    ///
+    /// # Returns
+    /// **KeyResult<String>** - The PEM block of the key or an error if the primes or the public exponent are unknown
+    pub fn to_pem(self: &Self, format: KeyFormat) -> KeyResult<String> {
+        let label: &str = match format {
+            KeyFormat::Pkcs1 => PKCS1_PRIVATE_LABEL,
+            KeyFormat::Pkcs8 => PKCS8_PRIVATE_LABEL,
+        };
+        Ok(encode_pem(label, &Zeroizing::new(self.to_der(format)?)))
+    }
+
+    /// Decode a PEM private key
+    ///
+    /// # Arguments
+    /// data: **&str** - The PEM text
+    ///
+    /// # Returns
+    /// **KeyResult<PrivateKey>** - The key decoded
+    pub fn from_pem(data: &str) -> KeyResult<Self> {
+        match decode_pem(data)? {
+            (label, der) if label == PKCS1_PRIVATE_LABEL || label == PKCS8_PRIVATE_LABEL => {
+                PrivateKey::from_der(&Zeroizing::new(der))
+            }
+            _ => Err(KeyError::InvalidEncoding),
+        }
+    }
+
+    /// Save the private key to a file
+    ///
+    /// This function will write the key as DER if the file has a `.der` extension, as PEM otherwise.
+    /// On unix the file is only readable and writable by its owner.
+    ///
+    /// # Arguments
+    /// path: **&Path** - The path of the key file<br/>
+    /// format: **KeyFormat** - The structure used to encode the key
+    ///
+    /// # Returns
+    /// **KeyResult<()>** - An error if the key can not be exported or the file could not be written
+    pub fn save(self: &Self, path: &Path, format: KeyFormat) -> KeyResult<()> {
+        let data: Zeroizing<Vec<u8>> = Zeroizing::new(match is_der_file(path) {
+            true => self.to_der(format)?,
+            false => self.to_pem(format)?.into_bytes(),
+        });
+        let mut options: fs::OpenOptions = fs::OpenOptions::new();
+
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut file: fs::File = options.open(path)?;
+        #[cfg(unix)]
+        file.set_permissions(std::os::unix::fs::PermissionsExt::from_mode(0o600))?;
+        file.write_all(&data)?;
+        Ok(())
+    }
+
+    /// Load a private key from a file
+    ///
+    /// This function will read a PEM or DER file in any of the supported formats
+    ///
+    /// # Arguments
+    /// path: **&Path** - The path of the key file
+    ///
+    /// # Returns
+    /// **KeyResult<PrivateKey>** - The key loaded
+    pub fn load(path: &Path) -> KeyResult<Self> {
+        match read_key_file(path)? {
+            KeyFile::Pem(text) => PrivateKey::from_pem(&Zeroizing::new(text)),
+            KeyFile::Der(der) => PrivateKey::from_der(&Zeroizing::new(der)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys_generator::test_keys::{
+        OPENSSL_FINGERPRINT, OPENSSL_PKCS1_PRIVATE, OPENSSL_PKCS1_PUBLIC, OPENSSL_PKCS8_PRIVATE,
+        OPENSSL_PKCS8_PUBLIC,
+    };
+
+    #[test]
+    fn test_openssl_private_key_round_trip() {
+        let key: PrivateKey = PrivateKey::from_pem(OPENSSL_PKCS8_PRIVATE).unwrap();
+        assert_eq!(key.encryption_value(), Some(BigUint::from(65537u32)));
+        assert_eq!(key.to_pem(KeyFormat::Pkcs8).unwrap(), OPENSSL_PKCS8_PRIVATE);
+        assert_eq!(key.to_pem(KeyFormat::Pkcs1).unwrap(), OPENSSL_PKCS1_PRIVATE);
+
+        let key: PrivateKey = PrivateKey::from_pem(OPENSSL_PKCS1_PRIVATE).unwrap();
+        assert_eq!(key.to_pem(KeyFormat::Pkcs8).unwrap(), OPENSSL_PKCS8_PRIVATE);
+        let der: Vec<u8> = key.to_der(KeyFormat::Pkcs1).unwrap();
+        assert_eq!(
+            PrivateKey::from_der(&der).unwrap().decryption_value(),
+            key.decryption_value()
+        );
+    }
+
+    #[test]
+    fn test_openssl_public_key_round_trip() {
+        let key: PublicKey = PublicKey::from_pem(OPENSSL_PKCS8_PUBLIC).unwrap();
+        assert_eq!(key.to_pem(KeyFormat::Pkcs8), OPENSSL_PKCS8_PUBLIC);
+        assert_eq!(key.to_pem(KeyFormat::Pkcs1), OPENSSL_PKCS1_PUBLIC);
+        assert_eq!(PublicKey::from_pem(OPENSSL_PKCS1_PUBLIC).unwrap(), key);
+        assert_eq!(
+            PublicKey::from_der(&key.to_der(KeyFormat::Pkcs1)).unwrap(),
+            key
+        );
+
+        let private_key: PrivateKey = PrivateKey::from_pem(OPENSSL_PKCS1_PRIVATE).unwrap();
+        assert_eq!(private_key.public_key().as_ref(), Some(&key));
+        assert_eq!(key.fingerprint(), OPENSSL_FINGERPRINT);
+    }
+
+    #[test]
+    fn test_invalid_keys() {
+        assert!(matches!(
+            PublicKey::from_pem(OPENSSL_PKCS8_PRIVATE),
+            Err(KeyError::InvalidEncoding)
+        ));
+        assert!(matches!(
+            PrivateKey::from_der(&[0x30, 0x03, 0x02, 0x01]),
+            Err(KeyError::InvalidEncoding)
+        ));
+        let truncated: &str = &OPENSSL_PKCS1_PRIVATE[..OPENSSL_PKCS1_PRIVATE.len() - 40];
+        assert!(PrivateKey::from_pem(truncated).is_err());
+
+        let key: PrivateKey = PrivateKey::new(&BigUint::from(413u32), &BigUint::from(3233u32));
+        assert!(matches!(
+            key.to_der(KeyFormat::Pkcs8),
+            Err(KeyError::MissingComponents)
+        ));
+    }
+
+    #[test]
+    fn test_degenerate_primes() {
+        // p = 1 and q = n, then p = q = n = 0, each of them multiplying to the modulus
+        for (modulus, p, q) in [(3233u32, 1u32, 3233u32), (0, 0, 0)] {
+            let der: Vec<u8> = write_sequence(
+                &[0, modulus, 17, 413, p, q, 0, 0, 0]
+                    .map(|value: u32| write_integer(&BigUint::from(value))),
+            );
+            assert!(matches!(
+                PrivateKey::from_der(&der),
+                Err(KeyError::InvalidKey(ValidationError::NotPrime))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_key_files() {
+        let directory: std::path::PathBuf = std::env::temp_dir();
+        let pem_path: std::path::PathBuf =
+            directory.join(format!("ip-tunnel-test-key-{}.pem", std::process::id()));
+        let der_path: std::path::PathBuf =
+            directory.join(format!("ip-tunnel-test-key-{}.der", std::process::id()));
+        let key: PrivateKey = PrivateKey::from_pem(OPENSSL_PKCS8_PRIVATE).unwrap();
+
+        key.save(&pem_path, KeyFormat::Pkcs8).unwrap();
+        key.save(&der_path, KeyFormat::Pkcs1).unwrap();
+        assert_eq!(
+            PrivateKey::load(&pem_path)
+                .unwrap()
+                .to_pem(KeyFormat::Pkcs8)
+                .unwrap(),
+            OPENSSL_PKCS8_PRIVATE
+        );
+        assert_eq!(
+            PrivateKey::load(&der_path)
+                .unwrap()
+                .to_pem(KeyFormat::Pkcs1)
+                .unwrap(),
+            OPENSSL_PKCS1_PRIVATE
+        );
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode: u32 = fs::metadata(&pem_path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        fs::remove_file(pem_path).unwrap();
+        fs::remove_file(der_path).unwrap();
+    }
+}
@@ -0,0 +1,105 @@
+/// Errors that can occur while importing or exporting keys.
+///
+/// This enum is used to represent the different errors that can occur while handling key files.
+///
+/// # Variants
+/// - **InvalidEncoding** - The data is not a valid PEM or DER encoded RSA key
+/// - **UnsupportedAlgorithm** - The key is not an RSA key
+/// - **MissingComponents** - The private key does not hold every value needed to export it
+/// - **InvalidKey** - The key is well encoded but is not a sound RSA key
+/// - **Io** - The key file could not be read or written
+#[derive(Debug)]
+pub enum KeyError {
+    InvalidEncoding,
+    UnsupportedAlgorithm,
+    MissingComponents,
+    InvalidKey(ValidationError),
+    Io(std::io::Error),
+}
+
+impl std::fmt::Display for KeyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KeyError::InvalidEncoding => write!(f, "invalid key encoding"),
+            KeyError::UnsupportedAlgorithm => write!(f, "not an RSA key"),
+            KeyError::MissingComponents => {
+                write!(f, "private key misses the primes or the public exponent")
+            }
+            KeyError::InvalidKey(err) => write!(f, "invalid key: {}", err),
+            KeyError::Io(err) => write!(f, "i/o error: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for KeyError {}
+
+impl From<std::io::Error> for KeyError {
+    fn from(err: std::io::Error) -> Self {
+        KeyError::Io(err)
+    }
+}
+
+impl From<ValidationError> for KeyError {
+    fn from(err: ValidationError) -> Self {
+        KeyError::InvalidKey(err)
+    }
+}
+
+/// Result type for the key files.
+///
+/// This type is used to represent the result of importing or exporting keys.
+pub type KeyResult<T> = std::result::Result<T, KeyError>;
+
+/// Reasons a key is rejected by the validation.
+///
+/// This enum is used to represent the different checks an RSA key can fail.
+///
+/// # Variants
+/// - **ModulusTooSmall** - The modulus is shorter than the minimum size
+/// - **InvalidModulusSize** - The modulus does not have the size that was requested
+/// - **InvalidPublicExponent** - The public exponent is even, too small or not lower than the modulus
+/// - **MissingComponents** - The private key does not hold its primes or its public exponent
+/// - **NotPrime** - One of the factors of the modulus is not prime
+/// - **EqualPrimes** - Both factors of the modulus are the same prime
+/// - **PrimesTooClose** - The factors are close enough for the modulus to be factored
+/// - **ModulusMismatch** - The product of the primes is not the modulus
+/// - **InvalidPrivateExponent** - The decryption value is not the inverse of the public exponent modulo λ(n)
+/// - **InvalidCrtValues** - The CRT values stored with the key do not match its primes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ValidationError {
+    ModulusTooSmall,
+    InvalidModulusSize,
+    InvalidPublicExponent,
+    MissingComponents,
+    NotPrime,
+    EqualPrimes,
+    PrimesTooClose,
+    ModulusMismatch,
+    InvalidPrivateExponent,
+    InvalidCrtValues,
+}
+
+impl std::fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let reason: &str = match self {
+            ValidationError::ModulusTooSmall => "modulus too small",
+            ValidationError::InvalidModulusSize => "modulus of the wrong size",
+            ValidationError::InvalidPublicExponent => "invalid public exponent",
+            ValidationError::MissingComponents => "primes or public exponent missing",
+            ValidationError::NotPrime => "factor of the modulus is not prime",
+            ValidationError::EqualPrimes => "both primes are equal",
+            ValidationError::PrimesTooClose => "primes too close to each other",
+            ValidationError::ModulusMismatch => "primes do not multiply to the modulus",
+            ValidationError::InvalidPrivateExponent => "private exponent does not match",
+            ValidationError::InvalidCrtValues => "CRT values do not match the primes",
+        };
+        write!(f, "{}", reason)
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+/// Result type for the key validation.
+///
+/// This type is used to represent the result of checking a key.
+pub type ValidationResult<T> = std::result::Result<T, ValidationError>;
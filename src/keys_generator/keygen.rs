@@ -0,0 +1,55 @@
+use std::path::{Path, PathBuf};
+
+use crate::keys_generator::{
+    encoding::{public_key_path, KeyFormat},
+    keys::{generate_keys, KeyOptions, PrivateKey, PublicKey},
+};
+
+/// Parse a key format name
+///
+/// This function will convert the name given on the command line into a key format
+///
+/// # Arguments
+/// name: **&str** - The name of the format, `pkcs1` or `pkcs8`
+///
+/// # Returns
+/// **Option<KeyFormat>** - The format, None if the name is unknown
+pub fn parse_format(name: &str) -> Option<KeyFormat> {
+    match name.to_ascii_lowercase().as_str() {
+        "pkcs1" => Some(KeyFormat::Pkcs1),
+        "pkcs8" => Some(KeyFormat::Pkcs8),
+        _ => None,
+    }
+}
+
+/// Start the key generation
+///
+/// This function will generate a keypair, write the private key to `path` (readable by its owner only)
+/// and the public key next to it in a `.pub` file, then print the fingerprint of the public key
+///
+/// # Arguments
+/// options: **&KeyOptions** - The size of the modulus and the public exponent<br/>
+/// path: **&Path** - The file receiving the private key<br/>
+/// format: **KeyFormat** - The encoding of both key files
+pub fn start_keygen(options: &KeyOptions, path: &Path, format: KeyFormat) {
+    if path.exists() {
+        panic!("Refusing to overwrite {}", path.display());
+    }
+    println!(
+        "Generating a {} bits RSA keypair with public exponent {}...",
+        options.modulus_size().bits(),
+        options.public_exponent()
+    );
+    let (public_key, private_key): (PublicKey, PrivateKey) = generate_keys(options);
+    let public_path: PathBuf = public_key_path(path);
+
+    private_key
+        .save(path, format)
+        .expect("Failed to save the private key...");
+    public_key
+        .save(&public_path, format)
+        .expect("Failed to save the public key...");
+    println!("Private key saved to {}", path.display());
+    println!("Public key saved to {}", public_path.display());
+    println!("Fingerprint: {}", public_key.fingerprint());
+}
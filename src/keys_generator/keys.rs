@@ -1,7 +1,79 @@
-use super::{private_keys::generate_private_key, public_keys::generate_public_key};
+use super::{
+    private_keys::generate_private_key,
+    public_keys::generate_public_key,
+    validation::{is_probable_prime, validate_generated_key},
+};
 use num_bigint::BigUint;
-use num_primes::Generator;
+use num_integer::Integer;
+use num_traits::One;
+use rand::{rngs::OsRng, RngCore};
 use serde::{Deserialize, Serialize};
+use std::{fmt, ops::Deref};
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+/// Public exponent of the keys generated by default
+pub const DEFAULT_PUBLIC_EXPONENT: u32 = 65537;
+
+/// Wipe a number
+///
+/// This function will overwrite the digits of the number with zeros and leave it equal to zero.
+/// `BigUint` gives no mutable access to its digits, but assigning it a slice of the same size
+/// rewrites them in place, in the allocation that already holds them.
+///
+/// # Arguments
+/// value: **&mut BigUint** - The number to wipe
+pub fn wipe(value: &mut BigUint) {
+    let zeros: Vec<u32> = vec![0; value.bits().div_ceil(32) as usize];
+
+    value.assign_from_slice(&zeros);
+}
+
+/// Secret number
+///
+/// This struct holds a number that must not outlive its use, like a private exponent or a prime.
+/// It is wiped when dropped and is never printed.
+pub struct SecretNumber(BigUint);
+
+impl SecretNumber {
+    /// Create a new secret number
+    ///
+    /// # Arguments
+    /// value: **BigUint** - The number to protect
+    ///
+    /// # Returns
+    /// **SecretNumber** - The secret number created
+    pub fn new(value: BigUint) -> Self {
+        return SecretNumber(value);
+    }
+}
+
+impl Deref for SecretNumber {
+    type Target = BigUint;
+
+    fn deref(&self) -> &BigUint {
+        &self.0
+    }
+}
+
+impl Zeroize for SecretNumber {
+    fn zeroize(&mut self) {
+        wipe(&mut self.0);
+    }
+}
+
+impl Drop for SecretNumber {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl ZeroizeOnDrop for SecretNumber {}
+
+impl fmt::Debug for SecretNumber {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("SecretNumber(<redacted>)")
+    }
+}
 
 /// Public key used in the RSA algorithm
 ///
@@ -22,11 +94,86 @@ pub struct PublicKey {
 ///
 /// # Fields
 /// - **decryption_value** - The value used to decrypt the data<br/>
-/// - **modulus** - The modulus of the private key
-#[derive(Debug)]
+/// - **modulus** - The modulus of the private key<br/>
+/// - **encryption_value** - The public exponent, when known<br/>
+/// - **crt** - The primes of the modulus and the values derived from them, when known
+///
+/// The secret components are wiped when the key is dropped and are left out of its `Debug` output.
 pub struct PrivateKey {
-    decryption_value: Vec<u8>,
-    modulus: Vec<u8>,
+    decryption_value: SecretNumber,
+    modulus: BigUint,
+    encryption_value: Option<BigUint>,
+    crt: Option<CrtValues>,
+}
+
+/// Values used by the Chinese Remainder Theorem
+///
+/// This struct is used to store the values that split a private key operation into two half size operations
+///
+/// # Fields
+/// - **p** - The first prime of the modulus<br/>
+/// - **q** - The second prime of the modulus<br/>
+/// - **dp** - The decryption value modulo p - 1<br/>
+/// - **dq** - The decryption value modulo q - 1<br/>
+/// - **qinv** - The inverse of q modulo p
+///
+/// Every value gives away the factorization of the modulus, so they are wiped when dropped and never printed.
+#[derive(Clone, PartialEq)]
+pub struct CrtValues {
+    pub p: BigUint,
+    pub q: BigUint,
+    pub dp: BigUint,
+    pub dq: BigUint,
+    pub qinv: BigUint,
+}
+
+impl CrtValues {
+    /// Compute the CRT values
+    ///
+    /// This function will derive dP, dQ and qInv from the primes and the decryption value
+    ///
+    /// # Arguments
+    /// decryption_value: **&BigUint** - The value used to decrypt the data<br/>
+    /// p: **&BigUint** - The first prime of the modulus<br/>
+    /// q: **&BigUint** - The second prime of the modulus
+    ///
+    /// # Returns
+    /// **Option<CrtValues>** - The values computed, None if q has no inverse modulo p
+    pub fn new(decryption_value: &BigUint, p: &BigUint, q: &BigUint) -> Option<Self> {
+        let qinv: BigUint = q.modinv(p)?;
+
+        return Some(CrtValues {
+            p: p.clone(),
+            q: q.clone(),
+            dp: decryption_value % (p - BigUint::one()),
+            dq: decryption_value % (q - BigUint::one()),
+            qinv,
+        });
+    }
+}
+
+impl Zeroize for CrtValues {
+    fn zeroize(&mut self) {
+        wipe(&mut self.p);
+        wipe(&mut self.q);
+        wipe(&mut self.dp);
+        wipe(&mut self.dq);
+        wipe(&mut self.qinv);
+    }
+}
+
+impl Drop for CrtValues {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl ZeroizeOnDrop for CrtValues {}
+
+impl fmt::Debug for CrtValues {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("CrtValues(<redacted>)")
+    }
 }
 
 impl PrivateKey {
@@ -42,8 +189,37 @@ impl PrivateKey {
     /// **PrivateKey** - The private key created
     pub fn new(decryption_value: &BigUint, modulus: &BigUint) -> Self {
         return PrivateKey {
-            decryption_value: decryption_value.to_bytes_be(),
-            modulus: modulus.to_bytes_be(),
+            decryption_value: SecretNumber::new(decryption_value.clone()),
+            modulus: modulus.clone(),
+            encryption_value: None,
+            crt: None,
+        };
+    }
+
+    /// Create a new private key from its primes
+    ///
+    /// This function will create a private key that remembers every component needed to export it
+    /// and to decrypt with the Chinese Remainder Theorem
+    ///
+    /// # Arguments
+    /// encryption_value: **&BigUint** - The public exponent<br/>
+    /// decryption_value: **&BigUint** - The value used to decrypt the data<br/>
+    /// p: **&BigUint** - The first prime of the modulus<br/>
+    /// q: **&BigUint** - The second prime of the modulus
+    ///
+    /// # Returns
+    /// **PrivateKey** - The private key created
+    pub fn from_primes(
+        encryption_value: &BigUint,
+        decryption_value: &BigUint,
+        p: &BigUint,
+        q: &BigUint,
+    ) -> Self {
+        return PrivateKey {
+            decryption_value: SecretNumber::new(decryption_value.clone()),
+            modulus: p * q,
+            encryption_value: Some(encryption_value.clone()),
+            crt: CrtValues::new(decryption_value, p, q),
         };
     }
 
@@ -54,17 +230,58 @@ impl PrivateKey {
     /// # Returns
     /// **BigUint** - The modulus of the private key
     pub fn modulus(self: &Self) -> BigUint {
-        BigUint::from_bytes_be(&self.modulus)
+        self.modulus.clone()
     }
 
     /// Get the decryption value
     ///
-    /// This function will return the decryption value of the private key
+    /// This function will borrow the decryption value of the private key, so that no copy of it is left behind
+    ///
+    /// # Returns
+    /// **&BigUint** - The decryption value of the private key
+    pub fn decryption_value(self: &Self) -> &BigUint {
+        &self.decryption_value
+    }
+
+    /// Get the encryption value
+    ///
+    /// This function will return the public exponent matching the private key
+    ///
+    /// # Returns
+    /// **Option<BigUint>** - The public exponent, None if the key was built without it
+    pub fn encryption_value(self: &Self) -> Option<BigUint> {
+        self.encryption_value.clone()
+    }
+
+    /// Get the CRT values
+    ///
+    /// This function will return the primes of the modulus and the values derived from them
     ///
     /// # Returns
-    /// **BigUint** - The decryption value of the private key
-    pub fn decryption_value(self: &Self) -> BigUint {
-        BigUint::from_bytes_be(&self.decryption_value)
+    /// **Option<&CrtValues>** - The CRT values, None if the key was built without its primes
+    pub fn crt(self: &Self) -> Option<&CrtValues> {
+        self.crt.as_ref()
+    }
+
+    /// Get the public key
+    ///
+    /// This function will return the public key matching the private key
+    ///
+    /// # Returns
+    /// **Option<PublicKey>** - The public key, None if the public exponent is unknown
+    pub fn public_key(self: &Self) -> Option<PublicKey> {
+        self.encryption_value
+            .as_ref()
+            .map(|encryption_value| PublicKey::new(encryption_value, &self.modulus))
+    }
+}
+
+impl fmt::Debug for PrivateKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PrivateKey")
+            .field("modulus", &self.modulus)
+            .field("encryption_value", &self.encryption_value)
+            .finish_non_exhaustive()
     }
 }
 
@@ -115,34 +332,234 @@ impl PublicKey {
 /// - **p** - The first prime number<br/>
 /// - **q** - The second prime number<br/>
 /// - **modulus** - The modulus of the base
+///
+/// The primes are wiped when the base is dropped.
 pub struct PrimeBase {
     pub p: BigUint,
     pub q: BigUint,
     pub modulus: BigUint,
 }
 
+impl Drop for PrimeBase {
+    fn drop(&mut self) {
+        wipe(&mut self.p);
+        wipe(&mut self.q);
+    }
+}
+
+/// Size of the modulus of a generated key
+///
+/// # Variants
+/// - **Bits2048** - A 2048 bits modulus, the default
+/// - **Bits3072** - A 3072 bits modulus
+/// - **Bits4096** - A 4096 bits modulus
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ModulusSize {
+    #[default]
+    Bits2048,
+    Bits3072,
+    Bits4096,
+}
+
+impl ModulusSize {
+    /// Get the size from a number of bits
+    ///
+    /// # Arguments
+    /// bits: **usize** - The number of bits of the modulus
+    ///
+    /// # Returns
+    /// **Option<ModulusSize>** - The size, None if it is not supported
+    pub fn from_bits(bits: usize) -> Option<Self> {
+        match bits {
+            2048 => Some(ModulusSize::Bits2048),
+            3072 => Some(ModulusSize::Bits3072),
+            4096 => Some(ModulusSize::Bits4096),
+            _ => None,
+        }
+    }
+
+    /// Get the number of bits
+    ///
+    /// # Returns
+    /// **usize** - The number of bits of the modulus
+    pub fn bits(self: &Self) -> usize {
+        match self {
+            ModulusSize::Bits2048 => 2048,
+            ModulusSize::Bits3072 => 3072,
+            ModulusSize::Bits4096 => 4096,
+        }
+    }
+}
+
+/// Options of the key generation
+///
+/// This struct is used to choose the shape of the keys generated
+///
+/// # Fields
+/// - **modulus_size** - The size of the modulus<br/>
+/// - **public_exponent** - The public exponent, 65537 by default
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyOptions {
+    modulus_size: ModulusSize,
+    public_exponent: BigUint,
+}
+
+impl KeyOptions {
+    /// Create new key options
+    ///
+    /// This function will check the public exponent is odd and greater than 2, so that it can be coprime with the totient
+    ///
+    /// # Arguments
+    /// modulus_size: **ModulusSize** - The size of the modulus<br/>
+    /// public_exponent: **BigUint** - The public exponent
+    ///
+    /// # Returns
+    /// **Option<KeyOptions>** - The options created, None if the public exponent can not be used
+    pub fn new(modulus_size: ModulusSize, public_exponent: BigUint) -> Option<Self> {
+        if public_exponent < BigUint::from(3u32) || public_exponent.is_even() {
+            return None;
+        }
+        return Some(KeyOptions {
+            modulus_size,
+            public_exponent,
+        });
+    }
+
+    /// Get the modulus size
+    ///
+    /// # Returns
+    /// **ModulusSize** - The size of the modulus
+    pub fn modulus_size(self: &Self) -> ModulusSize {
+        self.modulus_size
+    }
+
+    /// Get the public exponent
+    ///
+    /// # Returns
+    /// **&BigUint** - The public exponent
+    pub fn public_exponent(self: &Self) -> &BigUint {
+        &self.public_exponent
+    }
+}
+
+impl Default for KeyOptions {
+    fn default() -> Self {
+        return KeyOptions {
+            modulus_size: ModulusSize::default(),
+            public_exponent: BigUint::from(DEFAULT_PUBLIC_EXPONENT),
+        };
+    }
+}
+
+/// Generate a prime
+///
+/// This function will draw odd candidates whose two top bits are set, so that the product of two of them has exactly twice their size,
+/// until one passes the Miller-Rabin test
+///
+/// # Arguments
+/// bits: **usize** - The size of the prime, in bits
+///
+/// # Returns
+/// **BigUint** - The prime generated
+fn generate_prime(bits: usize) -> BigUint {
+    let mut bytes: Vec<u8> = vec![0; bits.div_ceil(8)];
+
+    loop {
+        OsRng.fill_bytes(&mut bytes);
+        let mut candidate: BigUint = BigUint::from_bytes_be(&bytes) >> (bytes.len() * 8 - bits);
+        candidate.set_bit(0, true);
+        candidate.set_bit(bits as u64 - 1, true);
+        candidate.set_bit(bits as u64 - 2, true);
+        if is_probable_prime(&candidate) {
+            return candidate;
+        }
+    }
+}
+
 /// Generate a base for the RSA algorithm
 ///
 /// This function will generate a base for the RSA algorithm
 ///
+/// # Arguments
+/// modulus_size: **ModulusSize** - The size of the modulus to generate
+///
 /// # Returns
 /// **PrimeBase** - The base generated
-fn generate_base() -> PrimeBase {
-    let p: BigUint = BigUint::from_bytes_be(&Generator::new_prime(1024).to_bytes_be());
-    let q: BigUint = BigUint::from_bytes_be(&Generator::new_prime(1024).to_bytes_be());
+fn generate_base(modulus_size: ModulusSize) -> PrimeBase {
+    let p: BigUint = generate_prime(modulus_size.bits() / 2);
+    let mut q: BigUint = generate_prime(modulus_size.bits() / 2);
+
+    while q == p {
+        q = generate_prime(modulus_size.bits() / 2);
+    }
     let modulus: BigUint = &p * &q;
     PrimeBase { p, q, modulus }
 }
 
 /// Generate a public and private key
 ///
-/// Using the RSA algorithm, this function will generate a public and private key
+/// Using the RSA algorithm, this function will generate a public and private key.
+/// New primes are drawn until the public exponent is coprime with the totient and the key passes the validation suite.
+///
+/// # Arguments
+/// options: **&KeyOptions** - The size of the modulus and the public exponent
 ///
 /// # Returns
 /// **(PublicKey, PrivateKey)** - The public and private key generated
-pub fn generate_keys() -> (PublicKey, PrivateKey) {
-    let base: PrimeBase = generate_base();
-    let public_key: (PublicKey, BigUint) = generate_public_key(&base);
-    let private_key: PrivateKey = generate_private_key(&public_key.0, &public_key.1, &base.modulus);
-    (public_key.0, private_key)
+pub fn generate_keys(options: &KeyOptions) -> (PublicKey, PrivateKey) {
+    loop {
+        let base: PrimeBase = generate_base(options.modulus_size());
+
+        if let Some((public_key, totient)) = generate_public_key(&base, options.public_exponent()) {
+            let totient: SecretNumber = SecretNumber::new(totient);
+            let private_key: PrivateKey = generate_private_key(&public_key, &totient, &base);
+
+            if validate_generated_key(&private_key, options).is_ok() {
+                return (public_key, private_key);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secrets_are_wiped_and_redacted() {
+        let mut value: BigUint = BigUint::from(u128::MAX);
+        wipe(&mut value);
+        assert_eq!(value, BigUint::from(0u32));
+
+        let private_key: PrivateKey = PrivateKey::from_primes(
+            &BigUint::from(17u32),
+            &BigUint::from(413u32),
+            &BigUint::from(61u32),
+            &BigUint::from(53u32),
+        );
+        let debug: String = format!("{:?}", private_key);
+        assert!(debug.contains("3233"));
+        assert!(!debug.contains("413"));
+        assert_eq!(
+            format!("{:?}", private_key.crt()),
+            "Some(CrtValues(<redacted>))"
+        );
+    }
+
+    #[test]
+    fn test_key_options() {
+        assert_eq!(ModulusSize::from_bits(3072), Some(ModulusSize::Bits3072));
+        assert_eq!(ModulusSize::from_bits(1024), None);
+        assert!(KeyOptions::new(ModulusSize::Bits2048, BigUint::from(3u32)).is_some());
+        assert!(KeyOptions::new(ModulusSize::Bits2048, BigUint::from(1u32)).is_none());
+        assert!(KeyOptions::new(ModulusSize::Bits2048, BigUint::from(65536u32)).is_none());
+
+        let (public_key, private_key) = generate_keys(&KeyOptions::default());
+        assert_eq!(public_key.modulus().bits(), 2048);
+        assert_eq!(
+            public_key.encryption_value(),
+            BigUint::from(DEFAULT_PUBLIC_EXPONENT)
+        );
+        assert_eq!(private_key.public_key(), Some(public_key));
+    }
 }
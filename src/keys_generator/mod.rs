@@ -1,3 +1,9 @@
+pub mod encoding;
+pub mod errors;
+pub mod keygen;
 pub mod keys;
 mod private_keys;
 mod public_keys;
+#[cfg(test)]
+pub mod test_keys;
+pub mod validation;
@@ -1,6 +1,6 @@
 use num_bigint::BigUint;
 
-use super::keys::{PrivateKey, PublicKey};
+use super::keys::{PrimeBase, PrivateKey, PublicKey, SecretNumber};
 
 /// Generate a private key from a public key
 ///
@@ -9,13 +9,19 @@ use super::keys::{PrivateKey, PublicKey};
 /// # Arguments
 /// public_key: **&PublicKey** - The public key to generate the private key from<br/>
 /// r: **BigUint** - The value of the totient function of the public key modulus<br/>
-/// modulus: **BigUint** - The value of the public key modulus
+/// base: **&PrimeBase** - The primes of the public key modulus
 ///
 /// # Returns
 /// **PrivateKey** - The private key generated from the public key
-pub fn generate_private_key(public_key: &PublicKey, r: &BigUint, modulus: &BigUint) -> PrivateKey {
-    let decryption: BigUint = public_key.encryption_value().modinv(&r).unwrap();
-    return PrivateKey::new(&decryption, &modulus);
+pub fn generate_private_key(public_key: &PublicKey, r: &BigUint, base: &PrimeBase) -> PrivateKey {
+    let decryption: SecretNumber =
+        SecretNumber::new(public_key.encryption_value().modinv(r).unwrap());
+    return PrivateKey::from_primes(
+        &public_key.encryption_value(),
+        &decryption,
+        &base.p,
+        &base.q,
+    );
 }
 
 #[cfg(test)]
@@ -25,9 +31,14 @@ mod tests {
     #[test]
     fn test_generate_private_key() {
         let public_key: PublicKey = PublicKey::new(&BigUint::from(17u32), &BigUint::from(3233u32));
-        let result: PrivateKey =
-            generate_private_key(&public_key, &BigUint::from(780u32), &BigUint::from(3233u32));
-        assert_eq!(result.decryption_value(), BigUint::from(413u32));
+        let base: PrimeBase = PrimeBase {
+            p: BigUint::from(61u32),
+            q: BigUint::from(53u32),
+            modulus: BigUint::from(3233u32),
+        };
+        let result: PrivateKey = generate_private_key(&public_key, &BigUint::from(780u32), &base);
+        assert_eq!(result.decryption_value(), &BigUint::from(413u32));
         assert_eq!(result.modulus(), BigUint::from(3233u32));
+        assert_eq!(result.public_key(), Some(public_key));
     }
 }
@@ -22,19 +22,39 @@ fn generate_totient(base: &PrimeBase) -> BigUint {
 /// Using the RSA algorithm, this function will generate a public key
 ///
 /// # Arguments
-/// base: **&PrimeBase** - The base to generate the public key from
+/// base: **&PrimeBase** - The base to generate the public key from<br/>
+/// public_exponent: **&BigUint** - The public exponent of the key
 ///
 /// # Returns
-/// **(PublicKey, BigUint)** - The public key generated and the value of the totient of the base
-pub fn generate_public_key(base: &PrimeBase) -> (PublicKey, BigUint) {
+/// **Option<(PublicKey, BigUint)>** - The public key generated and the value of the totient of the base,
+/// None if the exponent is not coprime with the totient and new primes are needed
+pub fn generate_public_key(
+    base: &PrimeBase,
+    public_exponent: &BigUint,
+) -> Option<(PublicKey, BigUint)> {
     let r: BigUint = generate_totient(base);
-    let mut e: BigUint = BigUint::from(2_u32);
-    while e < r {
-        if e.gcd(&r) == BigUint::one() {
-            break;
-        }
-        e += BigUint::one();
+
+    if public_exponent.gcd(&r) != BigUint::one() {
+        return None;
     }
-    // let e = BigUint::from(65537_u32);
-    return (PublicKey::new(&e, &base.modulus), r);
-}
\ No newline at end of file
+    return Some((PublicKey::new(public_exponent, &base.modulus), r));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_public_key() {
+        let base: PrimeBase = PrimeBase {
+            p: BigUint::from(61u32),
+            q: BigUint::from(53u32),
+            modulus: BigUint::from(3233u32),
+        };
+        let (public_key, r) = generate_public_key(&base, &BigUint::from(17u32)).unwrap();
+
+        assert_eq!(r, BigUint::from(780u32));
+        assert_eq!(public_key.encryption_value(), BigUint::from(17u32));
+        assert!(generate_public_key(&base, &BigUint::from(13u32)).is_none());
+    }
+}
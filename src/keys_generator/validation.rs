@@ -0,0 +1,257 @@
+//! Checks that RSA keys are sound, for generated and imported keys alike
+
+use std::sync::OnceLock;
+
+use num_bigint::BigUint;
+use num_integer::Integer;
+use num_traits::{One, Zero};
+use rand::{rngs::OsRng, RngCore};
+
+use super::{
+    errors::{ValidationError, ValidationResult},
+    keys::{CrtValues, KeyOptions, PrivateKey, PublicKey, SecretNumber},
+};
+
+/// Smallest modulus accepted, in bits
+pub const MIN_MODULUS_BITS: u64 = 2048;
+
+/// Number of Miller-Rabin rounds, each one letting a composite through with a probability below 1/4
+pub const MILLER_RABIN_ROUNDS: usize = 40;
+
+/// The primes must differ in one of their top bits but this many (FIPS 186-4, B.3.1)
+const PRIME_DISTANCE_MARGIN: u64 = 100;
+
+/// Bound of the primes used for trial division
+const SMALL_PRIMES_BOUND: usize = 2000;
+
+/// Get the small primes
+///
+/// This function will sieve the primes below `SMALL_PRIMES_BOUND` once and keep them
+///
+/// # Returns
+/// **&'static [u32]** - The small primes, in increasing order
+fn small_primes() -> &'static [u32] {
+    static SMALL_PRIMES: OnceLock<Vec<u32>> = OnceLock::new();
+
+    SMALL_PRIMES.get_or_init(|| {
+        let mut is_prime: Vec<bool> = vec![true; SMALL_PRIMES_BOUND];
+        let mut primes: Vec<u32> = Vec::new();
+
+        for n in 2..SMALL_PRIMES_BOUND {
+            if is_prime[n] {
+                primes.push(n as u32);
+                for multiple in (n * n..SMALL_PRIMES_BOUND).step_by(n) {
+                    is_prime[multiple] = false;
+                }
+            }
+        }
+        primes
+    })
+}
+
+/// Draw a random number
+///
+/// This function will draw a number below the bound from the operating system generator.
+/// Eight extra bytes are drawn so the bias of the reduction is negligible.
+///
+/// # Arguments
+/// bound: **&BigUint** - The exclusive upper bound, not zero
+///
+/// # Returns
+/// **BigUint** - The number drawn
+pub fn random_below(bound: &BigUint) -> BigUint {
+    let mut bytes: Vec<u8> = vec![0; bound.bits().div_ceil(8) as usize + 8];
+
+    OsRng.fill_bytes(&mut bytes);
+    BigUint::from_bytes_be(&bytes) % bound
+}
+
+/// Test if a number is prime
+///
+/// This function will run trial division by the small primes then `MILLER_RABIN_ROUNDS` Miller-Rabin rounds
+/// with bases drawn from the operating system generator
+///
+/// # Arguments
+/// candidate: **&BigUint** - The number to test
+///
+/// # Returns
+/// **bool** - True if the number is prime with overwhelming probability
+pub fn is_probable_prime(candidate: &BigUint) -> bool {
+    if *candidate < BigUint::from(2u32) {
+        return false;
+    }
+    for &prime in small_primes() {
+        if *candidate == BigUint::from(prime) {
+            return true;
+        }
+        if (candidate % prime).is_zero() {
+            return false;
+        }
+    }
+    let candidate_minus_one: BigUint = candidate - BigUint::one();
+    let shift: u64 = candidate_minus_one.trailing_zeros().unwrap_or(0);
+    let odd_part: BigUint = &candidate_minus_one >> shift;
+
+    'witness: for _ in 0..MILLER_RABIN_ROUNDS {
+        let base: BigUint = random_below(&(candidate - BigUint::from(3u32))) + BigUint::from(2u32);
+        let mut x: BigUint = base.modpow(&odd_part, candidate);
+
+        if x.is_one() || x == candidate_minus_one {
+            continue;
+        }
+        for _ in 1..shift {
+            x = x.modpow(&BigUint::from(2u32), candidate);
+            if x == candidate_minus_one {
+                continue 'witness;
+            }
+        }
+        return false;
+    }
+    true
+}
+
+/// Validate a public key
+///
+/// This function will check the size of the modulus and the public exponent
+///
+/// # Arguments
+/// key: **&PublicKey** - The key to check
+///
+/// # Returns
+/// **ValidationResult<()>** - The first check the key fails, if any
+pub fn validate_public_key(key: &PublicKey) -> ValidationResult<()> {
+    let modulus: BigUint = key.modulus();
+    let encryption_value: BigUint = key.encryption_value();
+
+    if modulus.bits() < MIN_MODULUS_BITS {
+        return Err(ValidationError::ModulusTooSmall);
+    }
+    if encryption_value < BigUint::from(3u32)
+        || encryption_value.is_even()
+        || encryption_value >= modulus
+    {
+        return Err(ValidationError::InvalidPublicExponent);
+    }
+    Ok(())
+}
+
+/// Validate a private key
+///
+/// This function will check the public part of the key, that its factors are two distinct and distant primes,
+/// that e·d ≡ 1 mod λ(n) and that the CRT values match the primes
+///
+/// # Arguments
+/// key: **&PrivateKey** - The key to check
+///
+/// # Returns
+/// **ValidationResult<()>** - The first check the key fails, if any
+pub fn validate_private_key(key: &PrivateKey) -> ValidationResult<()> {
+    let public_key: PublicKey = key.public_key().ok_or(ValidationError::MissingComponents)?;
+    let crt: &CrtValues = key.crt().ok_or(ValidationError::MissingComponents)?;
+    let modulus: BigUint = key.modulus();
+    let decryption_value: &BigUint = key.decryption_value();
+
+    validate_public_key(&public_key)?;
+    if &crt.p * &crt.q != modulus {
+        return Err(ValidationError::ModulusMismatch);
+    }
+    if crt.p == crt.q {
+        return Err(ValidationError::EqualPrimes);
+    }
+    let distance: BigUint = if crt.p > crt.q {
+        &crt.p - &crt.q
+    } else {
+        &crt.q - &crt.p
+    };
+    if distance.bits() <= (modulus.bits() / 2).saturating_sub(PRIME_DISTANCE_MARGIN) {
+        return Err(ValidationError::PrimesTooClose);
+    }
+    if !is_probable_prime(&crt.p) || !is_probable_prime(&crt.q) {
+        return Err(ValidationError::NotPrime);
+    }
+    let lambda: SecretNumber =
+        SecretNumber::new((&crt.p - BigUint::one()).lcm(&(&crt.q - BigUint::one())));
+    if !((public_key.encryption_value() * decryption_value) % &*lambda).is_one() {
+        return Err(ValidationError::InvalidPrivateExponent);
+    }
+    if CrtValues::new(decryption_value, &crt.p, &crt.q).as_ref() != Some(crt) {
+        return Err(ValidationError::InvalidCrtValues);
+    }
+    Ok(())
+}
+
+/// Validate a generated key
+///
+/// This function will check the private key and that its modulus has exactly the requested size
+///
+/// # Arguments
+/// key: **&PrivateKey** - The key generated<br/>
+/// options: **&KeyOptions** - The options the key was generated with
+///
+/// # Returns
+/// **ValidationResult<()>** - The first check the key fails, if any
+pub fn validate_generated_key(key: &PrivateKey, options: &KeyOptions) -> ValidationResult<()> {
+    if key.modulus().bits() != options.modulus_size().bits() as u64 {
+        return Err(ValidationError::InvalidModulusSize);
+    }
+    validate_private_key(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys_generator::test_keys::OPENSSL_PKCS1_PRIVATE;
+
+    #[test]
+    fn test_is_probable_prime() {
+        let mersenne: BigUint = (BigUint::one() << 127u32) - BigUint::one();
+
+        assert!(is_probable_prime(&BigUint::from(2u32)));
+        assert!(is_probable_prime(&BigUint::from(1999u32)));
+        assert!(is_probable_prime(&BigUint::from(2003u32)));
+        assert!(is_probable_prime(&mersenne));
+        assert!(!is_probable_prime(&BigUint::from(1u32)));
+        // Carmichael numbers fool the Fermat test but not Miller-Rabin
+        assert!(!is_probable_prime(&BigUint::from(561u32)));
+        assert!(!is_probable_prime(&BigUint::from(2465u32 * 2821u32)));
+        assert!(!is_probable_prime(&(&mersenne * &mersenne)));
+    }
+
+    #[test]
+    fn test_validate_keys() {
+        let key: PrivateKey = PrivateKey::from_pem(OPENSSL_PKCS1_PRIVATE).unwrap();
+        let crt: &CrtValues = key.crt().unwrap();
+        let e: BigUint = key.encryption_value().unwrap();
+        let d: &BigUint = key.decryption_value();
+
+        assert_eq!(validate_private_key(&key), Ok(()));
+        assert_eq!(
+            validate_public_key(&PublicKey::new(&e, &BigUint::from(3233u32))),
+            Err(ValidationError::ModulusTooSmall)
+        );
+        assert_eq!(
+            validate_public_key(&PublicKey::new(&BigUint::from(65536u32), &key.modulus())),
+            Err(ValidationError::InvalidPublicExponent)
+        );
+        assert_eq!(
+            validate_private_key(&PrivateKey::from_primes(
+                &e,
+                &(d + BigUint::from(2u32)),
+                &crt.p,
+                &crt.q
+            )),
+            Err(ValidationError::InvalidPrivateExponent)
+        );
+        let composite: BigUint = &crt.p + BigUint::from(2u32);
+        if !is_probable_prime(&composite) {
+            assert_eq!(
+                validate_private_key(&PrivateKey::from_primes(&e, d, &composite, &crt.q)),
+                Err(ValidationError::NotPrime)
+            );
+        }
+        assert_eq!(
+            validate_private_key(&PrivateKey::new(d, &key.modulus())),
+            Err(ValidationError::MissingComponents)
+        );
+    }
+}
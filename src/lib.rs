@@ -0,0 +1,29 @@
+//! Encrypted tunnel over any stream
+//!
+//! The client and the server authenticate each other with their RSA identity keys, agree on X25519 keys and
+//! exchange ChaCha20-Poly1305 sealed messages. `Client::connect` and `Server::accept` run the handshake over a stream
+//! and return a `Session`, which reads and writes like the stream itself. The asynchronous variants run over tokio streams.
+//! `oaep_encrypt` and `oaep_decrypt` seal data to an RSA key with OAEP (SHA-256), outside of any session.
+
+// The code base favours explicit returns, typed `self` receivers and upper case packet names
+#![allow(
+    clippy::needless_return,
+    clippy::needless_arbitrary_self_type,
+    clippy::upper_case_acronyms
+)]
+
+mod cypher;
+pub mod keys_generator;
+pub mod protocol;
+
+pub use cypher::{oaep_decrypt, oaep_encrypt, CypherError, CypherResult};
+pub use keys_generator::keys::{PrivateKey, PublicKey};
+pub use protocol::{
+    client::{
+        errors::TunnelError as ClientError,
+        known_hosts::{HostStatus, KnownHosts, ServerTrust},
+        tunnel::Client,
+    },
+    server::{authorized_keys::AuthorizedKeys, errors::TunnelError as ServerError, tunnel::Server},
+    AsyncReceiver, AsyncSender, AsyncSession, ProtocolError, RekeyPolicy, Session,
+};
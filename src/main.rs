@@ -1,26 +1,135 @@
-use std::env;
+use std::{env, path::PathBuf, time::Duration};
 
-mod cypher;
-mod keys_generator;
-mod protocol;
+use ip_tunnel::{
+    keys_generator::{
+        self,
+        encoding::KeyFormat,
+        keys::{KeyOptions, ModulusSize, DEFAULT_PUBLIC_EXPONENT},
+    },
+    protocol::{self, RekeyPolicy},
+};
+use num_bigint::BigUint;
+
+/// Remove an option from the arguments
+///
+/// This function will remove the option and the value following it from the arguments
+///
+/// # Arguments
+/// args: **&mut Vec<String>** - The arguments of the program<br/>
+/// name: **&str** - The name of the option, such as `--identity`
+///
+/// # Returns
+/// **Option<String>** - The value of the option if it was given
+fn take_option(args: &mut Vec<String>, name: &str) -> Option<String> {
+    let index: usize = args.iter().position(|arg| arg == name)?;
+
+    args.remove(index);
+    if index >= args.len() {
+        panic!("Missing value for option {}", name);
+    }
+    Some(args.remove(index))
+}
+
+/// Remove a flag from the arguments
+///
+/// This function will remove the flag from the arguments
+///
+/// # Arguments
+/// args: **&mut Vec<String>** - The arguments of the program<br/>
+/// name: **&str** - The name of the flag, such as `--strict`
+///
+/// # Returns
+/// **bool** - True if the flag was given
+fn take_flag(args: &mut Vec<String>, name: &str) -> bool {
+    let length: usize = args.len();
+
+    args.retain(|arg| arg != name);
+    args.len() != length
+}
 
 /// Starting point of the program
 ///
 /// It will run different code depending on the number of arguments
 /// If given 2 arguments (ip address and port), it will start a client
 /// If given 1 argument (port), it will start a server
+/// If given `keygen`, it will generate a keypair and print its fingerprint
+///
+/// The server accepts `--identity <file>` to use (or create) a persistent identity key,
+/// and `--authorized-keys <file>` to only accept clients proving they own one of the listed keys.
+/// It serves many clients at once, up to `--max-clients <n>`, and sends the lines entered to every one of them.
+/// The client accepts `--identity <file>` to authenticate with an existing identity key.
+/// It accepts `--server-key <file>` to only trust a server using that identity key.
+/// Otherwise it trusts servers on first use, recording them in `--known-hosts <file>` (`~/.ip-tunnel/known_hosts` by default),
+/// and `--strict` refuses servers that are not recorded yet.
+/// Both accept `--rekey-bytes <n>` and `--rekey-seconds <n>` to renew their sending keys after that many bytes or seconds.
+/// The keygen command accepts `--bits <2048|3072|4096>`, `--exponent <e>`, `--out <file>` and `--format <pkcs1|pkcs8>`.
 fn main() -> std::io::Result<()> {
-    let args: Vec<String> = env::args().collect();
+    let mut args: Vec<String> = env::args().collect();
+
+    if args.get(1).map(String::as_str) == Some("keygen") {
+        let modulus_size: ModulusSize = take_option(&mut args, "--bits")
+            .map(|bits| {
+                bits.parse()
+                    .ok()
+                    .and_then(ModulusSize::from_bits)
+                    .expect("Invalid argument: bits, expected 2048, 3072 or 4096")
+            })
+            .unwrap_or_default();
+        let public_exponent: BigUint = take_option(&mut args, "--exponent")
+            .map(|exponent| exponent.parse().expect("Invalid argument: exponent"))
+            .unwrap_or_else(|| BigUint::from(DEFAULT_PUBLIC_EXPONENT));
+        let options: KeyOptions = KeyOptions::new(modulus_size, public_exponent)
+            .expect("Invalid argument: exponent, expected an odd number greater than 2");
+        let out: PathBuf = take_option(&mut args, "--out")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from("identity.pem"));
+        let format: KeyFormat = take_option(&mut args, "--format")
+            .map(|name| {
+                keys_generator::keygen::parse_format(&name).expect("Invalid argument: format")
+            })
+            .unwrap_or(KeyFormat::Pkcs8);
+
+        keys_generator::keygen::start_keygen(&options, &out, format);
+        return Ok(());
+    }
+    let identity: Option<PathBuf> = take_option(&mut args, "--identity").map(PathBuf::from);
+    let server_key: Option<PathBuf> = take_option(&mut args, "--server-key").map(PathBuf::from);
+    let known_hosts: Option<PathBuf> = take_option(&mut args, "--known-hosts").map(PathBuf::from);
+    let strict: bool = take_flag(&mut args, "--strict");
+    let authorized_keys: Option<PathBuf> =
+        take_option(&mut args, "--authorized-keys").map(PathBuf::from);
+    let default_policy: RekeyPolicy = RekeyPolicy::default();
+    let rekey_policy: RekeyPolicy = RekeyPolicy::new(
+        take_option(&mut args, "--rekey-bytes")
+            .map(|bytes| bytes.parse().expect("Invalid argument: rekey-bytes"))
+            .unwrap_or(default_policy.max_bytes()),
+        take_option(&mut args, "--rekey-seconds")
+            .map(|seconds| {
+                Duration::from_secs(seconds.parse().expect("Invalid argument: rekey-seconds"))
+            })
+            .unwrap_or(default_policy.max_age()),
+    );
+    let max_clients: Option<usize> = take_option(&mut args, "--max-clients")
+        .map(|count| count.parse().expect("Invalid argument: max-clients"));
 
     if args.len() == 2 {
         protocol::server::run::start_server(
             String::from("127.0.0.1"),
             args[1].parse().expect("Invalid argument"),
+            identity,
+            authorized_keys,
+            rekey_policy,
+            max_clients,
         );
     } else {
         protocol::client::run::start_client(
             args[1].clone(),
             args[2].parse().expect("Invalid argument: port"),
+            server_key,
+            known_hosts,
+            strict,
+            identity,
+            rekey_policy,
         );
     }
     Ok(())
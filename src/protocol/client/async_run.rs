@@ -0,0 +1,127 @@
+//! Client side of the tunnel over an asynchronous stream
+//!
+//! The handshake runs the same state machine as the blocking client, only the transport awaits.
+
+use tokio::io::{AsyncRead, AsyncWrite};
+
+use crate::{
+    keys_generator::keys::{PrivateKey, PublicKey},
+    protocol::{
+        client::{
+            errors::TunnelResult, handshake::state::ClientHandshake, known_hosts::ServerTrust,
+        },
+        shared::{
+            async_dispatch::{receive_packet, send_packet},
+            async_session::AsyncSession,
+            channel::{RekeyPolicy, SecureChannel},
+            types::PacketType,
+        },
+    },
+};
+
+/// Handshake with the server
+///
+/// This function will drive the state machine of the client over the stream, from the hello to the result of the handshake
+///
+/// # Arguments
+/// stream: **&mut S** - The stream to the server<br/>
+/// machine: **&mut ClientHandshake** - The state machine of the client side of the handshake
+///
+/// # Returns
+/// **TunnelResult<SecureChannel>** - The encrypted channel keyed by the key schedule if the handshake succeed or an error if it failed
+pub async fn handshake<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: &mut S,
+    machine: &mut ClientHandshake<'_>,
+) -> TunnelResult<SecureChannel> {
+    machine.start();
+    loop {
+        for packet in machine.take_outgoing() {
+            send_packet(stream, &packet).await?;
+        }
+        let packet: PacketType = receive_packet(stream, machine.protocol_state(), Ok).await?;
+        if let Some(channel) = machine.handle(packet)? {
+            return Ok(channel);
+        }
+    }
+}
+
+/// Connect to the server
+///
+/// This function will run the handshake over the stream and open a session on it
+///
+/// # Arguments
+/// stream: **S** - The stream to the server<br/>
+/// server_trust: **&mut ServerTrust** - The way the identity key of the server is trusted<br/>
+/// identity: **Option<&(PublicKey, PrivateKey)>** - The long-term keys proving the identity of the client, if it has one<br/>
+/// rekey_policy: **RekeyPolicy** - When the client renews its sending keys
+///
+/// # Returns
+/// **TunnelResult<AsyncSession<S>>** - The session with the server or an error if the handshake failed
+pub async fn connect<S: AsyncRead + AsyncWrite + Unpin>(
+    mut stream: S,
+    server_trust: &mut ServerTrust,
+    identity: Option<&(PublicKey, PrivateKey)>,
+    rekey_policy: RekeyPolicy,
+) -> TunnelResult<AsyncSession<S>> {
+    let mut machine: ClientHandshake = ClientHandshake::new(server_trust, identity);
+    let channel: SecureChannel = handshake(&mut stream, &mut machine).await?;
+
+    Ok(AsyncSession::new(stream, channel, rekey_policy))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::io::{DuplexStream, ReadHalf, WriteHalf};
+
+    use super::*;
+    use crate::{
+        keys_generator::test_keys::OPENSSL_PKCS8_PRIVATE,
+        protocol::{
+            server::async_run::accept,
+            shared::async_session::{AsyncReceiver, AsyncSender},
+        },
+    };
+
+    #[tokio::test]
+    async fn test_async_tunnel() {
+        let private_key: PrivateKey = PrivateKey::from_pem(OPENSSL_PKCS8_PRIVATE).unwrap();
+        let server_identity: (PublicKey, PrivateKey) =
+            (private_key.public_key().unwrap(), private_key);
+        let mut server_trust: ServerTrust = ServerTrust::Pinned(server_identity.0.clone());
+        let policy: RekeyPolicy = RekeyPolicy::new(1, Duration::from_secs(3600));
+        let (client_stream, server_stream): (DuplexStream, DuplexStream) =
+            tokio::io::duplex(64 * 1024);
+
+        let (client, server) = tokio::join!(
+            connect(client_stream, &mut server_trust, None, policy),
+            accept(server_stream, &server_identity, None, policy)
+        );
+        let mut client: AsyncSession<DuplexStream> = client.unwrap();
+        let mut server: AsyncSession<DuplexStream> = server.unwrap();
+        client.send(b"Hello server").await.unwrap();
+        assert_eq!(server.receive().await.unwrap().unwrap(), b"Hello server");
+
+        // Each half goes on its own, so a side can send several messages in a row
+        let (mut client_sender, mut client_receiver): (
+            AsyncSender<WriteHalf<DuplexStream>>,
+            AsyncReceiver<ReadHalf<DuplexStream>>,
+        ) = client.split();
+        let (mut server_sender, mut server_receiver): (
+            AsyncSender<WriteHalf<DuplexStream>>,
+            AsyncReceiver<ReadHalf<DuplexStream>>,
+        ) = server.split();
+        client_sender.send(b"first").await.unwrap();
+        client_sender.send(b"second").await.unwrap();
+        server_sender.send(b"Hello client").await.unwrap();
+        assert_eq!(
+            client_receiver.receive().await.unwrap().unwrap(),
+            b"Hello client"
+        );
+        assert_eq!(server_receiver.receive().await.unwrap().unwrap(), b"first");
+        assert_eq!(server_receiver.receive().await.unwrap().unwrap(), b"second");
+        client_sender.close().await.unwrap();
+        assert!(server_receiver.receive().await.unwrap().is_none());
+    }
+}
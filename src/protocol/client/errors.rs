@@ -1,3 +1,8 @@
+use crate::{
+    cypher::CypherError,
+    protocol::shared::{dispatch::ProtocolState, errors::ProtocolError},
+};
+
 /// Errors that can occur during the tunneling process.
 ///
 /// This enum is used to represent the different errors that can occur during the tunneling process.
@@ -6,6 +11,18 @@
 /// - **InvalidData** - The data received is invalid
 /// - **UnexpectedValue** - The value received is unexpected
 /// - **HandshakeWentWrong** - The handshake went wrong
+/// - **ServerDisconnected** - The server closed the session or left
+/// - **ConnectionLost** - The connection to the server was lost without the server closing the session, holding the cause
+/// - **TamperedData** - An encrypted message was modified or forged
+/// - **ServerAuthenticationFailed** - The server could not prove its identity
+/// - **HostKeyMismatch** - The server identity key differs from the one recorded in the known hosts, holding the fingerprint recorded and the one received
+/// - **UnknownHost** - The server is not in the known hosts and strict mode refuses new hosts, holding the fingerprint of its key
+/// - **KnownHostsNotSaved** - A new server could not be recorded in the known hosts, holding the cause
+/// - **ReplayedMessage** - A message was replayed, dropped or reordered on its way from the server
+/// - **UnsupportedVersion** - The server only speaks a protocol version that is not supported, holding it
+/// - **NoCommonCipherSuite** - No cipher suite is supported by both sides
+/// - **UnexpectedPacket** - The server sent a packet the client was not waiting for, holding the state of the client and the name of the packet
+/// - **SigningFailed** - The identity key of the client could not sign the handshake, holding the cause
 #[derive(Debug)]
 pub enum TunnelError {
     InvalidData,
@@ -13,6 +30,93 @@ pub enum TunnelError {
     HandshakeWentWrong,
 
     ServerDisconnected,
+    ConnectionLost(ProtocolError),
+    TamperedData,
+    ServerAuthenticationFailed,
+    HostKeyMismatch(String, String),
+    UnknownHost(String),
+    KnownHostsNotSaved(std::io::Error),
+    ReplayedMessage,
+    UnsupportedVersion(u16),
+    NoCommonCipherSuite,
+    UnexpectedPacket(ProtocolState, &'static str),
+    SigningFailed(CypherError),
+}
+
+impl std::fmt::Display for TunnelError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TunnelError::InvalidData => write!(f, "invalid data received"),
+            TunnelError::UnexpectedValue => write!(f, "unexpected value received"),
+            TunnelError::HandshakeWentWrong => write!(f, "handshake went wrong"),
+            TunnelError::ServerDisconnected => write!(f, "server disconnected"),
+            TunnelError::ConnectionLost(_) => write!(f, "connection to the server lost"),
+            TunnelError::TamperedData => write!(f, "message failed authentication"),
+            TunnelError::ServerAuthenticationFailed => {
+                write!(f, "server could not prove its identity")
+            }
+            TunnelError::HostKeyMismatch(known, received) => write!(
+                f,
+                "server identity differs from the known hosts, expected {} but received {}",
+                known, received
+            ),
+            TunnelError::UnknownHost(fingerprint) => write!(
+                f,
+                "server with fingerprint {} is not in the known hosts",
+                fingerprint
+            ),
+            TunnelError::KnownHostsNotSaved(_) => {
+                write!(f, "could not save the server to the known hosts")
+            }
+            TunnelError::ReplayedMessage => write!(f, "message replayed, dropped or reordered"),
+            TunnelError::UnsupportedVersion(version) => {
+                write!(f, "protocol version {} is not supported", version)
+            }
+            TunnelError::NoCommonCipherSuite => write!(f, "no cipher suite in common"),
+            TunnelError::UnexpectedPacket(state, packet) => {
+                write!(f, "unexpected {} packet while {}", packet, state)
+            }
+            TunnelError::SigningFailed(_) => write!(f, "could not sign the handshake"),
+        }
+    }
+}
+
+impl std::error::Error for TunnelError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TunnelError::ConnectionLost(err) => Some(err),
+            TunnelError::SigningFailed(err) => Some(err),
+            TunnelError::KnownHostsNotSaved(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<ProtocolError> for TunnelError {
+    fn from(err: ProtocolError) -> Self {
+        match err {
+            ProtocolError::PeerClosed => TunnelError::ServerDisconnected,
+            err @ (ProtocolError::Disconnected | ProtocolError::Io(_)) => {
+                TunnelError::ConnectionLost(err)
+            }
+            ProtocolError::FrameTooLarge(_) | ProtocolError::InvalidData => {
+                TunnelError::InvalidData
+            }
+            ProtocolError::TamperedData => TunnelError::TamperedData,
+            ProtocolError::OutOfOrder(_, _) => TunnelError::ReplayedMessage,
+            ProtocolError::UnsupportedVersion(version) => TunnelError::UnsupportedVersion(version),
+            ProtocolError::NoCommonCipherSuite => TunnelError::NoCommonCipherSuite,
+            ProtocolError::UnexpectedPacket(state, packet) => {
+                TunnelError::UnexpectedPacket(state, packet)
+            }
+        }
+    }
+}
+
+impl From<CypherError> for TunnelError {
+    fn from(err: CypherError) -> Self {
+        TunnelError::SigningFailed(err)
+    }
 }
 
 /// Result type for the tunneling process.
@@ -1,3 +1,4 @@
 pub mod receive;
 pub mod send;
+pub mod state;
 pub mod validate;
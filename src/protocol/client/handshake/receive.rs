@@ -1,47 +1,47 @@
-use std::net::TcpStream;
+use num_bigint::BigUint;
 
-use serde::Deserialize;
-
-use crate::protocol::{
-    client::errors::{TunnelError, TunnelResult},
-    shared::{
-        constant::SERVER_MASTER_KEY_SIZE,
-        types::{HelloServerRequest, SharingCryptedPubKeyRequest},
+use crate::{
+    keys_generator::keys::PublicKey,
+    protocol::{
+        client::errors::{TunnelError, TunnelResult},
+        shared::{
+            constant::{KO_BYTES, OK_BYTES},
+            types::{HandshakeValidatedRequest, ServerSignatureRequest},
+        },
     },
 };
 
-/// Read the hello message from the server
+/// Get the identity key of the server
 ///
-/// This function will read the hello message from the server
+/// This function will decode the identity key the server sent along with its signature of the handshake transcript
 ///
 /// # Arguments
-/// stream: **&mut TcpStream** - The stream to the server
+/// request: **&ServerSignatureRequest** - The signature message received from the server
 ///
 /// # Returns
-/// **TunnelResult<[u8; SERVER_MASTER_KEY_SIZE]>** - The random bytes received from the server
-pub fn read_server_hello(stream: &mut TcpStream) -> TunnelResult<[u8; SERVER_MASTER_KEY_SIZE]> {
-    let mut de = serde_json::Deserializer::from_reader(stream);
+/// **PublicKey** - The identity key of the server
+pub fn server_identity(request: &ServerSignatureRequest) -> PublicKey {
+    let identity_key: (Vec<u8>, Vec<u8>) = request.identity_key();
 
-    match HelloServerRequest::deserialize(&mut de) {
-        Ok(buffer) => Ok(buffer.key()),
-        Err(_) => Err(TunnelError::InvalidData),
-    }
+    PublicKey::new(
+        &BigUint::from_bytes_be(&identity_key.0),
+        &BigUint::from_bytes_be(&identity_key.1),
+    )
 }
 
-/// Read the public key from the server
+/// Check if the handshake succeed
 ///
-/// This function will read the public key from the server
+/// This function will read the result the server sent at the end of the protocol
 ///
 /// # Arguments
-/// stream: **&mut TcpStream** - The stream to the server
+/// request: **&HandshakeValidatedRequest** - The result received from the server
 ///
 /// # Returns
-/// **TunnelResult<Vec<u8>>** - The cyphered public key received from the server
-pub fn read_server_cyphered_pub_key(stream: &mut TcpStream) -> TunnelResult<Vec<u8>> {
-    let mut de = serde_json::Deserializer::from_reader(stream);
-
-    match SharingCryptedPubKeyRequest::deserialize(&mut de) {
-        Ok(buffer) => Ok(buffer.crypted_pub_key()),
-        Err(_) => Err(TunnelError::InvalidData),
+/// **TunnelResult<bool>** - True if the handshake succeed, false otherwise or an error if the value received is unexpected
+pub fn handshake_succeed(request: &HandshakeValidatedRequest) -> TunnelResult<bool> {
+    match &request.status()[0..2] {
+        OK_BYTES => Ok(true),
+        KO_BYTES => Ok(false),
+        _ => Err(TunnelError::UnexpectedValue),
     }
 }
@@ -1,73 +1,74 @@
-use std::net::TcpStream;
-
-use rand::{rngs::ThreadRng, Rng};
+use rand::RngCore;
 
 use crate::{
-    cypher::enigma,
-    keys_generator::keys::PublicKey,
+    cypher::pss_sign,
+    keys_generator::keys::{PrivateKey, PublicKey},
     protocol::{
         client::errors::TunnelResult,
         shared::{
-            constant::{CLIENT_MASTER_KEY_SIZE, MASTER_KEY_SIZE},
-            types::{HelloClientRequest, KeysValidatedRequest, SharingPubKeyRequest},
+            constant::{
+                HELLO_RANDOM_SIZE, KEY_CONFIRMATION_SIZE, KEY_SHARE_SIZE, PROTOCOL_VERSION,
+            },
+            negotiation::offered_cipher_suites,
+            transcript::Transcript,
+            types::{ClientSignatureRequest, HelloClientRequest, KeysValidatedRequest},
         },
     },
 };
 
-/// Send the hello message to the server
+/// Build the hello message for the server
 ///
-/// This function will send the hello message to the server, first step of the handshake protocol
+/// This function will build the hello message for the server, first step of the handshake protocol.
+/// It offers the highest protocol version spoken and every cipher suite supported.
 ///
 /// # Arguments
-/// stream: **&mut TcpStream** - The stream to the server
+/// key_share: **[u8; KEY_SHARE_SIZE]** - The ephemeral X25519 public key of the client
 ///
 /// # Returns
-/// **TunnelResult<[u8; CLIENT_MASTER_KEY_SIZE]>** - The random bytes sent to the server
-pub fn send_hello(stream: &mut TcpStream) -> TunnelResult<[u8; CLIENT_MASTER_KEY_SIZE]> {
-    let mut rng: ThreadRng = rand::thread_rng();
-    let mut data: [u8; CLIENT_MASTER_KEY_SIZE] = [0; CLIENT_MASTER_KEY_SIZE];
-    data.copy_from_slice(
-        (0..CLIENT_MASTER_KEY_SIZE)
-            .map(|_| rng.gen_range(0..255))
-            .collect::<Vec<u8>>()
-            .as_slice(),
-    );
-    let buffer: HelloClientRequest = HelloClientRequest::new(data);
-    serde_json::to_writer(stream, &buffer).expect("Failed to send data to server...");
-    Ok(data)
+/// **HelloClientRequest** - The hello message to send to the server
+pub fn build_hello(key_share: [u8; KEY_SHARE_SIZE]) -> HelloClientRequest {
+    let mut random: [u8; HELLO_RANDOM_SIZE] = [0; HELLO_RANDOM_SIZE];
+
+    rand::thread_rng().fill_bytes(&mut random);
+    HelloClientRequest::new(PROTOCOL_VERSION, random, offered_cipher_suites(), key_share)
 }
 
-/// Send the public key to the server
+/// Build the key confirmation for the server
 ///
-/// This function will send the public key to the server
+/// This function will wrap the value proving the client derived the same secrets as the server
 ///
 /// # Arguments
-/// stream: **&mut TcpStream** - The stream to the server<br/>
-/// pub_key: **&PublicKey** - The public key to send
-pub fn send_public_key(stream: &mut TcpStream, pub_key: &PublicKey) {
-    let buffer: SharingPubKeyRequest = SharingPubKeyRequest::new((pub_key.encryption_value().to_bytes_be(), pub_key.modulus().to_bytes_be()));
-
-    serde_json::to_writer(stream, &buffer).expect("Failed to send data to server...");
+/// confirmation: **&[u8; KEY_CONFIRMATION_SIZE]** - The key confirmation
+///
+/// # Returns
+/// **KeysValidatedRequest** - The key confirmation to send to the server
+pub fn build_key_confirmation(confirmation: &[u8; KEY_CONFIRMATION_SIZE]) -> KeysValidatedRequest {
+    KeysValidatedRequest::new(confirmation.to_vec())
 }
 
-/// Send the master password cyphered to the server
+/// Build the signature of the handshake for the server
 ///
-/// This function will cypher the master password and send it to the server
+/// This function will sign the transcript of the handshake with the identity key of the client so the server can authenticate it.
+/// A client without identity sends an empty proof.
 ///
 /// # Arguments
-/// stream: **&mut TcpStream** - The stream to the server<br/>
-/// public_key: **&PublicKey** - The public key used to cypher the message<br/>
-/// password: **&[u8; MASTER_KEY_SIZE]** - The master password to cypher
-pub fn send_cyphered_master_password(
-    stream: &mut TcpStream,
-    public_key: &PublicKey,
-    password: &[u8; MASTER_KEY_SIZE],
-) {
-    let data: Vec<u8> = enigma(
-        &password.to_vec(),
-        &public_key.encryption_value(),
-        &public_key.modulus(),
-    );
-    let buffer: KeysValidatedRequest = KeysValidatedRequest::new(data);
-    serde_json::to_writer(stream, &buffer).expect("Failed to send data to server...");
+/// identity: **Option<&(PublicKey, PrivateKey)>** - The long-term keys of the client<br/>
+/// transcript: **&Transcript** - The transcript of the handshake, ending with the identity key of the client
+///
+/// # Returns
+/// **TunnelResult<ClientSignatureRequest>** - The proof to send to the server or an error if the identity key can not sign
+pub fn build_client_signature(
+    identity: Option<&(PublicKey, PrivateKey)>,
+    transcript: &Transcript,
+) -> TunnelResult<ClientSignatureRequest> {
+    match identity {
+        Some((public_key, private_key)) => Ok(ClientSignatureRequest::new(
+            Some((
+                public_key.encryption_value().to_bytes_be(),
+                public_key.modulus().to_bytes_be(),
+            )),
+            pss_sign(&transcript.digest(), private_key)?,
+        )),
+        None => Ok(ClientSignatureRequest::new(None, Vec::new())),
+    }
 }
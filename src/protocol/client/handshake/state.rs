@@ -0,0 +1,396 @@
+//! State machine of the client side of the handshake
+//!
+//! The machine never touches the network: it is fed the packets received and queues the packets to send,
+//! so it can be driven by any transport and tested on its own. Each state holds the secrets it needs,
+//! and a failure drops them and leaves the machine idle, ready to start over.
+
+use crate::{
+    cypher::pss_verify,
+    keys_generator::keys::{PrivateKey, PublicKey},
+    protocol::{
+        client::{
+            errors::{TunnelError, TunnelResult},
+            known_hosts::{HostStatus, ServerTrust},
+        },
+        shared::{
+            channel::{Role, SecureChannel},
+            dispatch::ProtocolState,
+            key_exchange::{EphemeralKey, MasterKey},
+            key_schedule::KeySchedule,
+            negotiation::check_server_choice,
+            transcript::Transcript,
+            types::{
+                ClientSignatureRequest, HelloClientRequest, PacketType, ServerSignatureRequest,
+            },
+        },
+    },
+};
+
+use super::{
+    receive::{handshake_succeed, server_identity},
+    send::{build_client_signature, build_hello, build_key_confirmation},
+};
+
+/// State of the client side of the handshake
+///
+/// # Variants
+/// - **Idle** - The handshake has not started or failed
+/// - **AwaitingServerHello** - The hello was sent, holding the ephemeral key, the cipher suites offered and the transcript
+/// - **AwaitingServerSignature** - The server hello was accepted, holding the master key and the transcript
+/// - **AwaitingHandshakeResult** - The signature and the key confirmation were sent, holding the secrets of the session
+/// - **Established** - The server accepted the handshake
+enum ClientState {
+    Idle,
+    AwaitingServerHello {
+        ephemeral_key: EphemeralKey,
+        offered: Vec<u16>,
+        transcript: Transcript,
+    },
+    AwaitingServerSignature {
+        master_key: MasterKey,
+        transcript: Transcript,
+    },
+    AwaitingHandshakeResult {
+        schedule: KeySchedule,
+    },
+    Established,
+}
+
+impl ClientState {
+    /// Get what the state is waiting for
+    ///
+    /// # Returns
+    /// **ProtocolState** - The state as reported to the dispatcher
+    fn protocol_state(self: &Self) -> ProtocolState {
+        match self {
+            ClientState::Idle => ProtocolState::Idle,
+            ClientState::AwaitingServerHello { .. } => ProtocolState::AwaitingServerHello,
+            ClientState::AwaitingServerSignature { .. } => ProtocolState::AwaitingServerSignature,
+            ClientState::AwaitingHandshakeResult { .. } => ProtocolState::AwaitingHandshakeResult,
+            ClientState::Established => ProtocolState::Established,
+        }
+    }
+}
+
+/// Client side of the handshake
+///
+/// # Fields
+/// - **state** - The current state of the handshake<br/>
+/// - **outgoing** - The packets waiting to be sent to the server<br/>
+/// - **server_trust** - The way the identity key of the server is trusted<br/>
+/// - **host_status** - How the identity key of the server was trusted, None until it is verified<br/>
+/// - **identity** - The long-term keys proving the identity of the client, if it has one
+pub struct ClientHandshake<'a> {
+    state: ClientState,
+    outgoing: Vec<PacketType>,
+    server_trust: &'a mut ServerTrust,
+    host_status: Option<HostStatus>,
+    identity: Option<&'a (PublicKey, PrivateKey)>,
+}
+
+impl<'a> ClientHandshake<'a> {
+    /// Create a new client handshake
+    ///
+    /// This function will create an idle handshake, started with `start`
+    ///
+    /// # Arguments
+    /// server_trust: **&mut ServerTrust** - The way the identity key of the server is trusted<br/>
+    /// identity: **Option<&(PublicKey, PrivateKey)>** - The long-term keys proving the identity of the client, if it has one
+    ///
+    /// # Returns
+    /// **ClientHandshake** - The handshake created
+    pub fn new(
+        server_trust: &'a mut ServerTrust,
+        identity: Option<&'a (PublicKey, PrivateKey)>,
+    ) -> Self {
+        return ClientHandshake {
+            state: ClientState::Idle,
+            outgoing: Vec::new(),
+            server_trust,
+            host_status: None,
+            identity,
+        };
+    }
+
+    /// Start the handshake
+    ///
+    /// This function will drop whatever an earlier attempt left, draw a new ephemeral key and queue the hello.
+    /// The server restarts as well when it receives it.
+    pub fn start(self: &mut Self) {
+        let ephemeral_key: EphemeralKey = EphemeralKey::new();
+        let client_hello: HelloClientRequest = build_hello(ephemeral_key.share());
+        let mut transcript: Transcript = Transcript::new();
+
+        transcript.update_packet(&PacketType::HELLOCLIENT(client_hello.clone()));
+        self.state = ClientState::AwaitingServerHello {
+            ephemeral_key,
+            offered: client_hello.cipher_suites().to_vec(),
+            transcript,
+        };
+        self.outgoing = vec![PacketType::HELLOCLIENT(client_hello)];
+        self.host_status = None;
+    }
+
+    /// Get what the handshake is waiting for
+    ///
+    /// # Returns
+    /// **ProtocolState** - The current state of the handshake
+    pub fn protocol_state(self: &Self) -> ProtocolState {
+        self.state.protocol_state()
+    }
+
+    /// Get how the server was trusted
+    ///
+    /// # Returns
+    /// **Option<&HostStatus>** - Whether the server was known or newly trusted, None until its identity key is verified
+    pub fn host_status(self: &Self) -> Option<&HostStatus> {
+        self.host_status.as_ref()
+    }
+
+    /// Take the packets to send
+    ///
+    /// # Returns
+    /// **Vec<PacketType>** - The packets queued since the last call, in the order they must be sent
+    pub fn take_outgoing(self: &mut Self) -> Vec<PacketType> {
+        std::mem::take(&mut self.outgoing)
+    }
+
+    /// Handle a packet from the server
+    ///
+    /// This function will move the handshake to its next state if the packet is the one expected.
+    /// Any error leaves the handshake idle.
+    ///
+    /// # Arguments
+    /// packet: **PacketType** - The packet received
+    ///
+    /// # Returns
+    /// **TunnelResult<Option<SecureChannel>>** - The encrypted channel once the server accepted the handshake,
+    /// None while it goes on, or an error if the packet is unexpected or the handshake failed
+    pub fn handle(self: &mut Self, packet: PacketType) -> TunnelResult<Option<SecureChannel>> {
+        match (
+            std::mem::replace(&mut self.state, ClientState::Idle),
+            packet,
+        ) {
+            (
+                ClientState::AwaitingServerHello {
+                    ephemeral_key,
+                    offered,
+                    mut transcript,
+                },
+                PacketType::HELLOSERVER(server_hello),
+            ) => {
+                check_server_choice(
+                    server_hello.version(),
+                    server_hello.cipher_suite(),
+                    &offered,
+                )?;
+                let master_key: MasterKey = ephemeral_key.agree(&server_hello.key_share())?;
+
+                transcript.update_packet(&PacketType::HELLOSERVER(server_hello));
+                self.state = ClientState::AwaitingServerSignature {
+                    master_key,
+                    transcript,
+                };
+                Ok(None)
+            }
+            (
+                ClientState::AwaitingServerSignature {
+                    master_key,
+                    transcript,
+                },
+                PacketType::SERVERSIGNATURE(server_signature),
+            ) => self.accept_server_signature(master_key, transcript, server_signature),
+            (
+                ClientState::AwaitingHandshakeResult { schedule },
+                PacketType::HANDSHAKEVALIDATED(result),
+            ) => {
+                if !handshake_succeed(&result)? {
+                    return Err(TunnelError::HandshakeWentWrong);
+                }
+                self.state = ClientState::Established;
+                Ok(Some(SecureChannel::new(&schedule, Role::Client)))
+            }
+            (state, packet) => Err(TunnelError::UnexpectedPacket(
+                state.protocol_state(),
+                packet.name(),
+            )),
+        }
+    }
+
+    /// Accept the signature of the server
+    ///
+    /// This function will authenticate the server, then queue the signature of the client and the key confirmation
+    ///
+    /// # Arguments
+    /// master_key: **MasterKey** - The master key agreed with the server<br/>
+    /// transcript: **Transcript** - The transcript of the handshake so far<br/>
+    /// server_signature: **ServerSignatureRequest** - The signature received from the server
+    ///
+    /// # Returns
+    /// **TunnelResult<Option<SecureChannel>>** - None or an error if the server could not be authenticated
+    fn accept_server_signature(
+        self: &mut Self,
+        master_key: MasterKey,
+        mut transcript: Transcript,
+        server_signature: ServerSignatureRequest,
+    ) -> TunnelResult<Option<SecureChannel>> {
+        let identity_key: PublicKey = server_identity(&server_signature);
+
+        transcript.update_key(&identity_key);
+        if !pss_verify(
+            &transcript.digest(),
+            &server_signature.signature(),
+            &identity_key,
+        ) {
+            return Err(TunnelError::ServerAuthenticationFailed);
+        }
+        self.host_status = Some(self.server_trust.verify(&identity_key)?);
+        transcript.update_packet(&PacketType::SERVERSIGNATURE(server_signature));
+        if let Some((client_identity, _)) = self.identity {
+            transcript.update_key(client_identity);
+        }
+        let client_signature: ClientSignatureRequest =
+            build_client_signature(self.identity, &transcript)?;
+        transcript.update_packet(&PacketType::CLIENTSIGNATURE(client_signature.clone()));
+        let schedule: KeySchedule = KeySchedule::new(&master_key, &transcript.digest());
+
+        self.outgoing
+            .push(PacketType::CLIENTSIGNATURE(client_signature));
+        self.outgoing
+            .push(PacketType::KEYSVALIDATED(build_key_confirmation(
+                schedule.key_confirmation(),
+            )));
+        self.state = ClientState::AwaitingHandshakeResult { schedule };
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        keys_generator::{
+            keys::PrivateKey,
+            test_keys::{OPENSSL_PKCS1_PRIVATE, OPENSSL_PKCS8_PRIVATE},
+        },
+        protocol::{
+            server::{authorized_keys::AuthorizedKeys, handshake::state::ServerHandshake},
+            shared::types::{DataRequest, KeysValidatedRequest},
+        },
+    };
+    use num_bigint::BigUint;
+
+    /// Load a test key as an identity
+    fn identity(pem: &str) -> (PublicKey, PrivateKey) {
+        let private_key: PrivateKey = PrivateKey::from_pem(pem).unwrap();
+        (private_key.public_key().unwrap(), private_key)
+    }
+
+    /// Deliver the packets of each side to the other until both hold a channel or one fails
+    fn run(
+        client: &mut ClientHandshake,
+        server: &mut ServerHandshake,
+    ) -> Result<(SecureChannel, SecureChannel), String> {
+        let mut client_channel: Option<SecureChannel> = None;
+        let mut server_channel: Option<SecureChannel> = None;
+
+        while client_channel.is_none() || server_channel.is_none() {
+            for packet in client.take_outgoing() {
+                server_channel = server.handle(packet).map_err(|err| err.to_string())?;
+            }
+            for packet in server.take_outgoing() {
+                client_channel = client.handle(packet).map_err(|err| err.to_string())?;
+            }
+        }
+        Ok((client_channel.unwrap(), server_channel.unwrap()))
+    }
+
+    #[test]
+    fn test_handshake_state_machines() {
+        let server_identity: (PublicKey, PrivateKey) = identity(OPENSSL_PKCS8_PRIVATE);
+        let mut server_trust: ServerTrust = ServerTrust::Pinned(server_identity.0.clone());
+        let mut client: ClientHandshake = ClientHandshake::new(&mut server_trust, None);
+        let mut server: ServerHandshake = ServerHandshake::new(&server_identity, None);
+
+        client.start();
+        assert!(matches!(
+            client.handle(PacketType::PING),
+            Err(TunnelError::UnexpectedPacket(
+                ProtocolState::AwaitingServerHello,
+                "PING"
+            ))
+        ));
+        assert_eq!(client.protocol_state(), ProtocolState::Idle);
+        let confirmation: PacketType = PacketType::KEYSVALIDATED(KeysValidatedRequest::new(vec![]));
+        assert!(server.handle(confirmation).is_err());
+        assert_eq!(server.protocol_state(), ProtocolState::AwaitingClientHello);
+
+        // An attempt abandoned halfway is superseded by the next hello
+        client.start();
+        for packet in client.take_outgoing() {
+            assert!(server.handle(packet).unwrap().is_none());
+        }
+        assert_eq!(
+            server.protocol_state(),
+            ProtocolState::AwaitingClientSignature
+        );
+        server.take_outgoing();
+        client.start();
+        let (client_channel, server_channel): (SecureChannel, SecureChannel) =
+            run(&mut client, &mut server).unwrap();
+        assert_eq!(client.protocol_state(), ProtocolState::Established);
+        assert_eq!(server.protocol_state(), ProtocolState::Established);
+
+        let request: DataRequest = client_channel.split().0.seal(b"Hello server");
+        assert_eq!(
+            server_channel.split().1.open(&request).unwrap(),
+            b"Hello server"
+        );
+        assert!(matches!(
+            client.handle(PacketType::PING),
+            Err(TunnelError::UnexpectedPacket(
+                ProtocolState::Established,
+                "PING"
+            ))
+        ));
+    }
+
+    #[test]
+    fn test_handshake_state_machines_authentication() {
+        let server_identity: (PublicKey, PrivateKey) = identity(OPENSSL_PKCS8_PRIVATE);
+        let client_identity: (PublicKey, PrivateKey) = identity(OPENSSL_PKCS1_PRIVATE);
+        let authorized_keys: AuthorizedKeys =
+            AuthorizedKeys::parse(&client_identity.0.fingerprint()).unwrap();
+        let mut server: ServerHandshake =
+            ServerHandshake::new(&server_identity, Some(&authorized_keys));
+
+        let mut server_trust: ServerTrust = ServerTrust::Pinned(server_identity.0.clone());
+        let mut anonymous: ClientHandshake = ClientHandshake::new(&mut server_trust, None);
+        anonymous.start();
+        assert_eq!(
+            run(&mut anonymous, &mut server).err().unwrap(),
+            "client could not prove it owns an authorized key: no identity key presented"
+        );
+        assert!(matches!(
+            anonymous.handle(server.take_outgoing().remove(0)),
+            Err(TunnelError::HandshakeWentWrong)
+        ));
+
+        let other_key: PublicKey =
+            PublicKey::new(&BigUint::from(65537_u32), &BigUint::from(3233_u32));
+        let mut server_trust: ServerTrust = ServerTrust::Pinned(other_key);
+        let mut mistrusting: ClientHandshake = ClientHandshake::new(&mut server_trust, None);
+        mistrusting.start();
+        assert_eq!(
+            run(&mut mistrusting, &mut server).err().unwrap(),
+            "server could not prove its identity"
+        );
+
+        // The server is ready for the next attempt
+        let mut server_trust: ServerTrust = ServerTrust::Pinned(server_identity.0.clone());
+        let mut client: ClientHandshake =
+            ClientHandshake::new(&mut server_trust, Some(&client_identity));
+        client.start();
+        assert!(run(&mut client, &mut server).is_ok());
+    }
+}
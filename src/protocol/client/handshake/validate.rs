@@ -1,76 +1,85 @@
-use std::net::TcpStream;
+use std::io::{Read, Write};
 
-use serde::Deserialize;
-
-use crate::{
-    cypher::enigma,
-    keys_generator::keys::{generate_keys, PrivateKey, PublicKey},
-    protocol::{
-        client::errors::TunnelResult,
-        shared::{
-            constant::{
-                CLIENT_MASTER_KEY_SIZE, KO_BYTES, MASTER_KEY_SIZE, OK_BYTES, SERVER_MASTER_KEY_SIZE,
-            },
-            types::HandshakeValidatedRequest,
-        },
+use crate::protocol::{
+    client::errors::TunnelResult,
+    shared::{
+        channel::SecureChannel,
+        dispatch::{receive_packet, send_packet},
+        types::PacketType,
     },
 };
 
-use super::{
-    receive::{read_server_cyphered_pub_key, read_server_hello},
-    send::{send_cyphered_master_password, send_hello, send_public_key},
-};
+use super::state::ClientHandshake;
 
-/// Check if the handshake succeed
+/// Handshake with the server
 ///
-/// This function will read the stream from the server and check if the handshake succeed at the end of the protocol
+/// This function will drive the state machine of the client over the stream, from the hello to the result of the handshake.
+/// The machine can be reused for a later attempt, which starts over from a clean state.
+/// Any blocking transport fits, such as a TCP or Unix socket, a pipe or a TLS session.
 ///
 /// # Arguments
-/// stream: **&mut TcpStream** - The stream to the server
+/// stream: **&mut S** - The stream to the server<br/>
+/// machine: **&mut ClientHandshake** - The state machine of the client side of the handshake
 ///
 /// # Returns
-/// **TunnelResult<bool>** - True if the handshake succeed, false otherwise or an error if the value received is unexpected
-fn handshake_succeed(stream: &mut TcpStream) -> TunnelResult<bool> {
-    let mut de = serde_json::Deserializer::from_reader(stream);
-    let buffer: HandshakeValidatedRequest = HandshakeValidatedRequest::deserialize(&mut de)
-        .expect("Invalid data received from server...");
-
-    match &buffer.status()[0..2] {
-        OK_BYTES => Ok(true),
-        KO_BYTES => Ok(false),
-        _ => Err(crate::protocol::client::errors::TunnelError::UnexpectedValue),
+/// **TunnelResult<SecureChannel>** - The encrypted channel keyed by the key schedule if the handshake succeed or an error if it failed
+pub fn handshake<S: Read + Write>(
+    stream: &mut S,
+    machine: &mut ClientHandshake,
+) -> TunnelResult<SecureChannel> {
+    machine.start();
+    loop {
+        for packet in machine.take_outgoing() {
+            send_packet(stream, &packet)?;
+        }
+        let packet: PacketType = receive_packet(stream, machine.protocol_state(), Ok)?;
+        if let Some(channel) = machine.handle(packet)? {
+            return Ok(channel);
+        }
     }
 }
 
-/// Handshake with the server
-///
-/// This function will perform the handshake protocol with the server
-///
-/// # Arguments
-/// stream: **&mut TcpStream** - The stream to the server
-///
-/// # Returns
-/// **TunnelResult<((PublicKey, PrivateKey), PublicKey)>** - The keys used during the handshake if the handshake succeed or an error if it failed
-pub fn handshake(stream: &mut TcpStream) -> TunnelResult<((PublicKey, PrivateKey), PublicKey)> {
-    let keys: (PublicKey, PrivateKey) = generate_keys();
-    let client_hello: [u8; CLIENT_MASTER_KEY_SIZE] = send_hello(stream)?;
-    let server_hello: [u8; SERVER_MASTER_KEY_SIZE] = read_server_hello(stream)?;
-    send_public_key(stream, &keys.0);
-    let cyphered_server_key: Vec<u8> = read_server_cyphered_pub_key(stream)?;
-    let server_key: Vec<u8> = enigma(
-        &cyphered_server_key,
-        &keys.1.decryption_value(),
-        &keys.1.modulus(),
-    );
-    let server_key: PublicKey = serde_json::from_slice(&server_key).unwrap();
-    let master_password: [u8; MASTER_KEY_SIZE] = [client_hello, server_hello].concat()
-        [0..MASTER_KEY_SIZE]
-        .try_into()
-        .unwrap();
-    send_cyphered_master_password(stream, &server_key, &master_password);
-    match handshake_succeed(stream) {
-        Ok(true) => Ok((keys, server_key)),
-        Ok(false) => Err(crate::protocol::client::errors::TunnelError::HandshakeWentWrong),
-        Err(x) => Err(x),
+#[cfg(all(test, unix))]
+mod tests {
+    use std::{os::unix::net::UnixStream, thread};
+
+    use super::*;
+    use crate::{
+        keys_generator::{
+            keys::{PrivateKey, PublicKey},
+            test_keys::OPENSSL_PKCS8_PRIVATE,
+        },
+        protocol::{
+            client::known_hosts::ServerTrust,
+            server::handshake::{state::ServerHandshake, validate::handshake as server_handshake},
+            shared::types::DataRequest,
+        },
+    };
+
+    #[test]
+    fn test_handshake_over_unix_socket() {
+        let private_key: PrivateKey = PrivateKey::from_pem(OPENSSL_PKCS8_PRIVATE).unwrap();
+        let server_identity: (PublicKey, PrivateKey) =
+            (private_key.public_key().unwrap(), private_key);
+        let mut server_trust: ServerTrust = ServerTrust::Pinned(server_identity.0.clone());
+        let (mut client_stream, mut server_stream): (UnixStream, UnixStream) =
+            UnixStream::pair().unwrap();
+
+        thread::scope(|scope| {
+            let server = scope.spawn(|| {
+                let mut machine: ServerHandshake = ServerHandshake::new(&server_identity, None);
+                server_handshake(&mut server_stream, &mut machine).map_err(|err| err.to_string())
+            });
+            let mut machine: ClientHandshake = ClientHandshake::new(&mut server_trust, None);
+            let client_channel: SecureChannel =
+                handshake(&mut client_stream, &mut machine).unwrap();
+            let server_channel: SecureChannel = server.join().unwrap().unwrap();
+
+            let request: DataRequest = client_channel.split().0.seal(b"Hello server");
+            assert_eq!(
+                server_channel.split().1.open(&request).unwrap(),
+                b"Hello server"
+            );
+        });
     }
 }
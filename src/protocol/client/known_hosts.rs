@@ -0,0 +1,247 @@
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    keys_generator::keys::PublicKey,
+    protocol::client::errors::{TunnelError, TunnelResult},
+};
+
+/// Name of the known hosts file, stored in the home directory
+pub const KNOWN_HOSTS_FILE: &str = ".ip-tunnel/known_hosts";
+
+/// Get the default known hosts file
+///
+/// This function will return the known hosts file of the user, falling back to the current directory if there is no home
+///
+/// # Returns
+/// **PathBuf** - The path of the known hosts file
+pub fn default_known_hosts_path() -> PathBuf {
+    match std::env::var_os("HOME") {
+        Some(home) => Path::new(&home).join(KNOWN_HOSTS_FILE),
+        None => PathBuf::from("known_hosts"),
+    }
+}
+
+/// Outcome of a host verified against the known hosts
+///
+/// # Variants
+/// - **Known** - The host was already trusted with this key
+/// - **NewlyTrusted** - The host was unknown and is now recorded, holding the fingerprint of its key
+#[derive(Debug, Clone, PartialEq)]
+pub enum HostStatus {
+    Known,
+    NewlyTrusted(String),
+}
+
+/// Store of the server keys already seen
+///
+/// This struct is used to remember the fingerprint of the identity key of every server the client connected to,
+/// one `host:port fingerprint` entry per line
+///
+/// # Fields
+/// - **path** - The file holding the entries<br/>
+/// - **entries** - The hosts and the fingerprints of their key<br/>
+/// - **strict** - Whether unknown hosts are refused instead of being trusted on first use
+pub struct KnownHosts {
+    path: PathBuf,
+    entries: Vec<(String, String)>,
+    strict: bool,
+}
+
+impl KnownHosts {
+    /// Load the known hosts
+    ///
+    /// This function will read the known hosts file, a missing file being treated as an empty store
+    ///
+    /// # Arguments
+    /// path: **&Path** - The known hosts file<br/>
+    /// strict: **bool** - Whether unknown hosts are refused
+    ///
+    /// # Returns
+    /// **io::Result<KnownHosts>** - The store or an error if the file could not be read
+    pub fn load(path: &Path, strict: bool) -> io::Result<Self> {
+        let content: String = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(err),
+        };
+        let entries: Vec<(String, String)> = content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter_map(|line| line.split_once(char::is_whitespace))
+            .map(|(host, fingerprint)| (host.to_string(), fingerprint.trim().to_string()))
+            .collect();
+
+        return Ok(KnownHosts {
+            path: path.to_path_buf(),
+            entries,
+            strict,
+        });
+    }
+
+    /// Get the fingerprint of a host
+    ///
+    /// This function will look for the fingerprint recorded for a host
+    ///
+    /// # Arguments
+    /// host: **&str** - The host, written `host:port`
+    ///
+    /// # Returns
+    /// **Option<&str>** - The fingerprint recorded, None if the host is unknown
+    pub fn fingerprint(self: &Self, host: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|(known_host, _)| known_host == host)
+            .map(|(_, fingerprint)| fingerprint.as_str())
+    }
+
+    /// Get the known hosts file
+    ///
+    /// # Returns
+    /// **&Path** - The file holding the entries
+    pub fn path(self: &Self) -> &Path {
+        &self.path
+    }
+
+    /// Record a host
+    ///
+    /// This function will append the fingerprint of a host to the known hosts file
+    ///
+    /// # Arguments
+    /// host: **&str** - The host, written `host:port`<br/>
+    /// fingerprint: **&str** - The fingerprint of its identity key
+    ///
+    /// # Returns
+    /// **io::Result<()>** - An error if the file could not be written
+    fn record(self: &mut Self, host: &str, fingerprint: &str) -> io::Result<()> {
+        if let Some(directory) = self.path.parent() {
+            fs::create_dir_all(directory)?;
+        }
+        let mut file: fs::File = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+
+        writeln!(file, "{} {}", host, fingerprint)?;
+        self.entries
+            .push((host.to_string(), fingerprint.to_string()));
+        Ok(())
+    }
+
+    /// Verify the identity key of a host
+    ///
+    /// This function will compare the key with the fingerprint recorded for the host.
+    /// An unknown host is recorded on first use, unless the store is strict.
+    ///
+    /// # Arguments
+    /// host: **&str** - The host, written `host:port`<br/>
+    /// key: **&PublicKey** - The identity key the server proved to own
+    ///
+    /// # Returns
+    /// **TunnelResult<HostStatus>** - Whether the host was known or newly trusted,
+    /// or an error if the key does not match, the host is refused or could not be recorded
+    pub fn verify(self: &mut Self, host: &str, key: &PublicKey) -> TunnelResult<HostStatus> {
+        let fingerprint: String = key.fingerprint();
+
+        match self.fingerprint(host) {
+            Some(known) if known == fingerprint => Ok(HostStatus::Known),
+            Some(known) => Err(TunnelError::HostKeyMismatch(known.to_string(), fingerprint)),
+            None if self.strict => Err(TunnelError::UnknownHost(fingerprint)),
+            None => {
+                self.record(host, &fingerprint)
+                    .map_err(TunnelError::KnownHostsNotSaved)?;
+                Ok(HostStatus::NewlyTrusted(fingerprint))
+            }
+        }
+    }
+}
+
+/// Way the client decides to trust the identity key of a server
+///
+/// # Variants
+/// - **Pinned** - Only the given key is accepted
+/// - **KnownHosts** - The key is checked against the known hosts store, for the given `host:port`
+pub enum ServerTrust {
+    Pinned(PublicKey),
+    KnownHosts(KnownHosts, String),
+}
+
+impl ServerTrust {
+    /// Verify the identity key of the server
+    ///
+    /// This function will check that the key the server proved to own can be trusted
+    ///
+    /// # Arguments
+    /// key: **&PublicKey** - The identity key of the server
+    ///
+    /// # Returns
+    /// **TunnelResult<HostStatus>** - Whether the server was known or newly trusted, a pinned key being known,
+    /// or an error if the key is not trusted
+    pub fn verify(self: &mut Self, key: &PublicKey) -> TunnelResult<HostStatus> {
+        match self {
+            ServerTrust::Pinned(expected) if expected == key => Ok(HostStatus::Known),
+            ServerTrust::Pinned(_) => Err(TunnelError::ServerAuthenticationFailed),
+            ServerTrust::KnownHosts(known_hosts, host) => known_hosts.verify(host, key),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_bigint::BigUint;
+
+    #[test]
+    fn test_known_hosts() {
+        let path: PathBuf =
+            std::env::temp_dir().join(format!("ip-tunnel-test-known-hosts-{}", std::process::id()));
+        let key: PublicKey = PublicKey::new(&BigUint::from(17u32), &BigUint::from(3233u32));
+        let other_key: PublicKey = PublicKey::new(&BigUint::from(7u32), &BigUint::from(3233u32));
+        let _ = fs::remove_file(&path);
+
+        let mut strict: KnownHosts = KnownHosts::load(&path, true).unwrap();
+        assert!(matches!(
+            strict.verify("127.0.0.1:7000", &key),
+            Err(TunnelError::UnknownHost(_))
+        ));
+
+        let mut known_hosts: KnownHosts = KnownHosts::load(&path, false).unwrap();
+        assert_eq!(
+            known_hosts.verify("127.0.0.1:7000", &key).unwrap(),
+            HostStatus::NewlyTrusted(key.fingerprint())
+        );
+
+        let mut known_hosts: KnownHosts = KnownHosts::load(&path, true).unwrap();
+        assert_eq!(
+            known_hosts.fingerprint("127.0.0.1:7000"),
+            Some(key.fingerprint().as_str())
+        );
+        assert_eq!(
+            known_hosts.verify("127.0.0.1:7000", &key).unwrap(),
+            HostStatus::Known
+        );
+        assert!(matches!(
+            known_hosts.verify("127.0.0.1:7000", &other_key),
+            Err(TunnelError::HostKeyMismatch(_, _))
+        ));
+        assert!(matches!(
+            known_hosts.verify("127.0.0.1:7001", &key),
+            Err(TunnelError::UnknownHost(_))
+        ));
+        fs::remove_file(&path).unwrap();
+
+        // A host that can not be recorded is not trusted
+        let mut known_hosts: KnownHosts = KnownHosts::load(&path, false).unwrap();
+        fs::create_dir(&path).unwrap();
+        assert!(matches!(
+            known_hosts.verify("127.0.0.1:7000", &key),
+            Err(TunnelError::KnownHostsNotSaved(_))
+        ));
+        assert_eq!(known_hosts.fingerprint("127.0.0.1:7000"), None);
+        fs::remove_dir(&path).unwrap();
+    }
+}
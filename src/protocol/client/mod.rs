@@ -1,3 +1,7 @@
-mod errors;
-mod handshake;
+pub mod async_run;
+pub mod errors;
+// Kept inside the crate, the tests of each side drive the handshake of the other
+pub(crate) mod handshake;
+pub mod known_hosts;
 pub mod run;
+pub mod tunnel;
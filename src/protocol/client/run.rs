@@ -1,138 +1,185 @@
-use std::{
-    io::{self, Read, Write},
-    net::TcpStream,
-};
+use std::{net::TcpStream, path::PathBuf};
 
 use crate::{
-    cypher::enigma,
     keys_generator::keys::{PrivateKey, PublicKey},
     protocol::{
-        client::{errors::TunnelError, handshake::validate::handshake},
-        shared::constant::MAX_PACKET_SIZE,
+        client::{
+            handshake::{state::ClientHandshake, validate::handshake},
+            known_hosts::{default_known_hosts_path, HostStatus, KnownHosts, ServerTrust},
+        },
+        shared::{
+            channel::{RekeyPolicy, SecureChannel},
+            conversation::{self, ConversationEnd},
+            errors::ProtocolError,
+            input::UserInput,
+        },
     },
 };
 
-use super::errors::TunnelResult;
-
-/// Send an input to the server
-///
-/// This function will ask the user for an input and send it to the server cyphered
-///
-/// # Arguments
-/// stream: **&mut TcpStream** - The stream to the server<br/>
-/// pub_key: **&PublicKey** - The public key used to cypher the message
-fn send_input(stream: &mut TcpStream, pub_key: &PublicKey) {
-    let mut input_buffer: String = String::new();
-    let stdin: io::Stdin = io::stdin();
-    let enigma_buffer: Vec<u8>;
-
-    print!("Localhost: ");
-    std::io::stdout().flush().unwrap();
-    stdin
-        .read_line(&mut input_buffer)
-        .expect("Error while reading standard input...");
-    enigma_buffer = enigma(
-        &input_buffer.as_bytes().to_vec(),
-        &pub_key.encryption_value(),
-        &pub_key.modulus(),
-    );
-    input_buffer = serde_json::to_string(&enigma_buffer).unwrap();
-    stream
-        .write(input_buffer.as_bytes())
-        .expect("Failed sending data to server...");
-}
-
-/// Read the stream from the server
-///
-/// This function will read the stream from the server and print the message received
-///
-/// # Arguments
-/// stream: **&mut TcpStream** - The stream to the server<br/>
-/// private_key: **&PrivateKey** - The private key used to decrypt the message
-fn read_stream(stream: &mut TcpStream, private_key: &PrivateKey) -> TunnelResult<()> {
-    let mut buffer: [u8; MAX_PACKET_SIZE] = [0; MAX_PACKET_SIZE];
-    let json_data: &str;
-    let cyphered_message: Vec<u8>;
-    let mut plain_message: Vec<u8>;
-    let data_size: usize;
-
-    data_size = stream
-        .read(&mut buffer)
-        .expect("Failed to read from server...");
-    json_data = std::str::from_utf8(&buffer[0..data_size]).unwrap();
-    if data_size == 0 {
-        return Err(TunnelError::ServerDisconnected);
-    }
-    cyphered_message = serde_json::from_str(json_data).unwrap();
-    plain_message = enigma(
-        &cyphered_message,
-        &private_key.decryption_value(),
-        &private_key.modulus(),
-    );
-    plain_message.pop();
-    println!(
-        "{}: [{}]",
-        stream.peer_addr().unwrap().ip(),
-        std::str::from_utf8(&plain_message.iter().map(|x| *x as u8).collect::<Vec<u8>>()).unwrap()
-    );
-    Ok(())
-}
+use super::errors::{TunnelError, TunnelResult};
 
 /// Initialize the communication with the handshake protocol
 ///
-/// This function will start the handshake protocol with the server
+/// This function will start the handshake protocol with the server.
+/// Failed handshakes can be retried from a clean state, unless the server speaks no version or cipher suite in common
+/// or left, as it does when it serves too many clients already.
 ///
 /// # Arguments
-/// stream: **&mut TcpStream** - The stream to the server   
+/// stream: **&mut TcpStream** - The stream to the server<br/>
+/// server_trust: **&mut ServerTrust** - The way the identity key of the server is trusted<br/>
+/// identity: **Option<&(PublicKey, PrivateKey)>** - The long-term keys of the client, if it has one
 ///
 /// # Returns
-/// **Option<((PublicKey, PrivateKey), PublicKey)>** - The keys used for the communication if the handshake is successful.<br/>
+/// **Option<SecureChannel>** - The channel used for the communication if the handshake is successful.<br/>
 /// None otherwise
-fn init_communication(stream: &mut TcpStream) -> Option<((PublicKey, PrivateKey), PublicKey)> {
+fn init_communication(
+    stream: &mut TcpStream,
+    server_trust: &mut ServerTrust,
+    identity: Option<&(PublicKey, PrivateKey)>,
+) -> Option<SecureChannel> {
     let mut input: String = String::new();
-    let mut keys: TunnelResult<((PublicKey, PrivateKey), PublicKey)> = handshake(stream);
+    let mut machine: ClientHandshake = ClientHandshake::new(server_trust, identity);
+    let mut keys: TunnelResult<SecureChannel> = handshake(stream, &mut machine);
 
-    while keys.is_err() {
-        println!("Handshake failed {:?}", keys.as_ref().unwrap_err());
+    while let Err(err) = &keys {
+        println!("Handshake failed: {}", err);
+        match err {
+            TunnelError::UnsupportedVersion(version) => {
+                println!(
+                    "Server selected protocol version {}, which is not supported",
+                    version
+                );
+                return None;
+            }
+            TunnelError::NoCommonCipherSuite => {
+                println!("No cipher suite in common with the server");
+                return None;
+            }
+            TunnelError::HostKeyMismatch(_, _) => {
+                if let ServerTrust::KnownHosts(known_hosts, host) = server_trust {
+                    println!(
+                        "WARNING: the identity key of {} changed!\nRemove its entry from {} if this change is expected.",
+                        host,
+                        known_hosts.path().display()
+                    );
+                }
+                return None;
+            }
+            TunnelError::KnownHostsNotSaved(cause) => {
+                println!("Failed to save the known hosts: {}", cause);
+                return None;
+            }
+            TunnelError::UnknownHost(_)
+            | TunnelError::ServerDisconnected
+            | TunnelError::ConnectionLost(_) => return None,
+            _ => (),
+        }
         println!("Should we retry the process ? Y/n");
+        input.clear();
         std::io::stdin()
             .read_line(&mut input)
             .expect("Error while reading standard input...");
-        if input == "Y" || input == "y" {
-            keys = handshake(stream);
+        if input.trim() == "Y" || input.trim() == "y" {
+            keys = handshake(stream, &mut machine);
         } else {
             return None;
         }
     }
+    let newly_trusted: Option<String> = match machine.host_status() {
+        Some(HostStatus::NewlyTrusted(fingerprint)) => Some(fingerprint.clone()),
+        _ => None,
+    };
+    if let (Some(fingerprint), ServerTrust::KnownHosts(_, host)) = (newly_trusted, server_trust) {
+        println!(
+            "Trusting new host {} with fingerprint {}",
+            host, fingerprint
+        );
+    }
     return Some(keys.unwrap());
 }
 
+/// Communicate with the server
+///
+/// This function will hold the conversation with the server, then tell how the session ended
+///
+/// # Arguments
+/// stream: **&mut TcpStream** - The stream to the server<br/>
+/// channel: **SecureChannel** - The channel keyed by the handshake<br/>
+/// rekey_policy: **RekeyPolicy** - When the client renews its sending keys<br/>
+/// input: **&UserInput** - The lines entered by the user
+fn communicate(
+    stream: &mut TcpStream,
+    channel: SecureChannel,
+    rekey_policy: RekeyPolicy,
+    input: &UserInput,
+) {
+    let server: String = stream
+        .peer_addr()
+        .map(|address| address.ip().to_string())
+        .unwrap_or_else(|_| String::from("server"));
+
+    match conversation::communicate(stream, channel, rekey_policy, input, &server) {
+        Ok(ConversationEnd::InputClosed) => (),
+        Ok(ConversationEnd::PeerClosed) => println!("{}", TunnelError::ServerDisconnected),
+        // Only a sealed close ends the session, a leave cuts it short like the stream ending
+        Err(err @ ProtocolError::PeerClosed) => println!("{}", TunnelError::ConnectionLost(err)),
+        Err(err) => println!("{}", TunnelError::from(err)),
+    }
+}
+
 /// Start the client
 ///
 /// This function will start the client and connect to the server
 ///
 /// # Arguments
 /// ip: **String** - The ip address of the server<br/>
-/// port: **u16** - The port of the server
-pub fn start_client(ip: String, port: u16) -> () {
+/// port: **u16** - The port of the server<br/>
+/// server_key: **Option<PathBuf>** - The file holding the identity key the server must use<br/>
+/// known_hosts: **Option<PathBuf>** - The known hosts file, used when no server key is given<br/>
+/// strict: **bool** - Whether servers missing from the known hosts are refused<br/>
+/// identity: **Option<PathBuf>** - The file holding the long-term private key of the client<br/>
+/// rekey_policy: **RekeyPolicy** - When the client renews its sending keys
+pub fn start_client(
+    ip: String,
+    port: u16,
+    server_key: Option<PathBuf>,
+    known_hosts: Option<PathBuf>,
+    strict: bool,
+    identity: Option<PathBuf>,
+    rekey_policy: RekeyPolicy,
+) {
     let endpoint: String = format!("{}:{}", ip, port);
     let mut stream: TcpStream =
         TcpStream::connect(endpoint.clone()).expect("Failed to connect to server...");
 
+    let mut server_trust: ServerTrust = match server_key {
+        Some(path) => ServerTrust::Pinned(
+            PublicKey::load(&path).expect("Failed to load the server public key..."),
+        ),
+        None => ServerTrust::KnownHosts(
+            KnownHosts::load(
+                &known_hosts.unwrap_or_else(default_known_hosts_path),
+                strict,
+            )
+            .expect("Failed to load the known hosts..."),
+            endpoint.clone(),
+        ),
+    };
+    let identity: Option<(PublicKey, PrivateKey)> = identity.map(|path| {
+        let private_key: PrivateKey =
+            PrivateKey::load(&path).expect("Failed to load the client identity...");
+        let public_key: PublicKey = private_key
+            .public_key()
+            .expect("Client identity has no public exponent...");
+        (public_key, private_key)
+    });
+
     println!("Client started and connected to {}!", endpoint);
 
-    let keys: ((PublicKey, PrivateKey), PublicKey) = match init_communication(&mut stream) {
-        None => return,
-        Some(keys) => keys,
-    };
-    loop {
-        send_input(&mut stream, &keys.1);
-        match read_stream(&mut stream, &keys.0 .1) {
-            Err(err) => {
-                println!("{:?}", err);
-                break;
-            }
-            Ok(_) => continue,
-        }
-    }
+    let channel: SecureChannel =
+        match init_communication(&mut stream, &mut server_trust, identity.as_ref()) {
+            None => return,
+            Some(channel) => channel,
+        };
+    communicate(&mut stream, channel, rekey_policy, &UserInput::spawn());
 }
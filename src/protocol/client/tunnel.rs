@@ -0,0 +1,142 @@
+use std::io::{Read, Write};
+
+use tokio::io::{AsyncRead, AsyncWrite};
+
+use crate::{
+    keys_generator::keys::{PrivateKey, PublicKey},
+    protocol::{
+        client::{
+            async_run,
+            errors::TunnelResult,
+            handshake::{state::ClientHandshake, validate::handshake},
+            known_hosts::ServerTrust,
+        },
+        shared::{
+            async_session::AsyncSession,
+            channel::{RekeyPolicy, SecureChannel},
+            session::Session,
+        },
+    },
+};
+
+/// Client side of the tunnel
+///
+/// This struct holds what the client needs to open sessions: the way it trusts servers and the keys proving its own identity.
+/// It never touches the network by itself, the caller hands it the stream to run over.
+///
+/// # Fields
+/// - **server_trust** - The way the identity key of the server is trusted<br/>
+/// - **identity** - The long-term keys proving the identity of the client, if it has one<br/>
+/// - **rekey_policy** - When the sessions renew their sending keys
+pub struct Client {
+    server_trust: ServerTrust,
+    identity: Option<(PublicKey, PrivateKey)>,
+    rekey_policy: RekeyPolicy,
+}
+
+impl Client {
+    /// Create a new client
+    ///
+    /// This function will create an anonymous client, renewing its keys with the default policy
+    ///
+    /// # Arguments
+    /// server_trust: **ServerTrust** - The way the identity key of the server is trusted
+    ///
+    /// # Returns
+    /// **Client** - The client created
+    pub fn new(server_trust: ServerTrust) -> Self {
+        return Client {
+            server_trust,
+            identity: None,
+            rekey_policy: RekeyPolicy::default(),
+        };
+    }
+
+    /// Set the identity of the client
+    ///
+    /// # Arguments
+    /// identity: **(PublicKey, PrivateKey)** - The long-term keys proving the identity of the client to the server
+    pub fn set_identity(self: &mut Self, identity: (PublicKey, PrivateKey)) {
+        self.identity = Some(identity);
+    }
+
+    /// Set the rekey policy
+    ///
+    /// # Arguments
+    /// policy: **RekeyPolicy** - When the sessions renew their sending keys
+    pub fn set_rekey_policy(self: &mut Self, policy: RekeyPolicy) {
+        self.rekey_policy = policy;
+    }
+
+    /// Connect to the server
+    ///
+    /// This function will run the handshake over the stream and open a session on it
+    ///
+    /// # Arguments
+    /// stream: **S** - The stream to the server, such as a `TcpStream`
+    ///
+    /// # Returns
+    /// **TunnelResult<Session<S>>** - The session with the server or an error if the handshake failed
+    pub fn connect<S: Read + Write>(self: &mut Self, mut stream: S) -> TunnelResult<Session<S>> {
+        let mut machine: ClientHandshake =
+            ClientHandshake::new(&mut self.server_trust, self.identity.as_ref());
+        let channel: SecureChannel = handshake(&mut stream, &mut machine)?;
+
+        Ok(Session::new(stream, channel, self.rekey_policy))
+    }
+
+    /// Connect to the server over an asynchronous stream
+    ///
+    /// # Arguments
+    /// stream: **S** - The stream to the server, such as a tokio `TcpStream`
+    ///
+    /// # Returns
+    /// **TunnelResult<AsyncSession<S>>** - The session with the server or an error if the handshake failed
+    pub async fn connect_async<S: AsyncRead + AsyncWrite + Unpin>(
+        self: &mut Self,
+        stream: S,
+    ) -> TunnelResult<AsyncSession<S>> {
+        async_run::connect(
+            stream,
+            &mut self.server_trust,
+            self.identity.as_ref(),
+            self.rekey_policy,
+        )
+        .await
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::{error::Error, os::unix::net::UnixStream};
+
+    use super::*;
+    use crate::{
+        keys_generator::test_keys::OPENSSL_PKCS8_PRIVATE,
+        protocol::{client::errors::TunnelError, shared::errors::ProtocolError},
+    };
+
+    /// Connect to a server that is gone already
+    fn connect_to_nobody() -> Result<(), Box<dyn Error>> {
+        let private_key: PrivateKey = PrivateKey::from_pem(OPENSSL_PKCS8_PRIVATE).unwrap();
+        let mut client: Client =
+            Client::new(ServerTrust::Pinned(private_key.public_key().unwrap()));
+        let (stream, _): (UnixStream, UnixStream) = UnixStream::pair()?;
+
+        client.connect(stream)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_client_error_source() {
+        let err: Box<dyn Error> = connect_to_nobody().unwrap_err();
+        let err: &TunnelError = err.downcast_ref().unwrap();
+
+        // The hello is written to a closed socket, the failure of which is kept down the chain
+        assert!(matches!(err, TunnelError::ConnectionLost(_)));
+        let cause: &(dyn Error + 'static) = err.source().unwrap();
+        assert!(matches!(cause.downcast_ref(), Some(ProtocolError::Io(_))));
+        let io_error: &std::io::Error = cause.source().unwrap().downcast_ref().unwrap();
+        assert_eq!(io_error.kind(), std::io::ErrorKind::BrokenPipe);
+    }
+}
@@ -2,18 +2,38 @@ pub mod client;
 pub mod server;
 mod shared;
 
+pub use shared::{
+    async_session::{AsyncReceiver, AsyncSender, AsyncSession},
+    channel::RekeyPolicy,
+    dispatch::ProtocolState,
+    errors::ProtocolError,
+    session::Session,
+};
+
 /*
 
-C -Hello-> S
-C <-Hello- S
-C -PubKey-> S
-C <CryptedPubKey- S
-C -CryptedMasterPassword> S
+C -Hello+Version+CipherSuites+KeyShare-> S
+C <-Hello+Version+CipherSuite+KeyShare- S
+   The server selects the version and the first suite offered it supports, or none and both sides stop
+C <Signature- S
+C -Signature> S
+   Both sides run the key schedule over the master key and the transcript of the packets above
+C -KeyConfirmation> S
 C <Ok/KO- S
+   Each side runs the handshake as a state machine, a packet out of order fails it and a new Hello starts over
 
 
 C -Crypted> S
 C <Crypted- S
 C -Crypted> S
+   Both sides send whenever they want, each direction being read on its own
+   Once its policy says so, a side sends Rekey as the last message under its current keys, then ratchets them
+C -Rekey> S
+C -Crypted> S
+   A side sends Ping to keep the session alive, and Close once it is done
+C <Ping- S
+C -Close> S
+
+Every packet travels in the PacketType envelope
 
 */
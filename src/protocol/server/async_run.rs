@@ -0,0 +1,73 @@
+//! Server side of the tunnel over an asynchronous stream
+//!
+//! The handshake runs the same state machine as the blocking server, only the transport awaits.
+
+use tokio::io::{AsyncRead, AsyncWrite};
+
+use crate::{
+    keys_generator::keys::{PrivateKey, PublicKey},
+    protocol::{
+        server::{
+            authorized_keys::AuthorizedKeys, errors::TunnelResult,
+            handshake::state::ServerHandshake,
+        },
+        shared::{
+            async_dispatch::{receive_packet, send_packet},
+            async_session::AsyncSession,
+            channel::{RekeyPolicy, SecureChannel},
+            types::PacketType,
+        },
+    },
+};
+
+/// Handshake with the client
+///
+/// This function will drive the state machine of the server over the stream, from the hello of the client to the result of the handshake.
+/// The packets queued by the machine are sent even when the handshake fails, so the client learns why.
+///
+/// # Arguments
+/// stream: **&mut S** - The stream to the client<br/>
+/// machine: **&mut ServerHandshake** - The state machine of the server side of the handshake
+///
+/// # Returns
+/// **TunnelResult<SecureChannel>** - The encrypted channel keyed by the key schedule if the handshake succeed or an error if it failed
+pub async fn handshake<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: &mut S,
+    machine: &mut ServerHandshake<'_>,
+) -> TunnelResult<SecureChannel> {
+    loop {
+        let packet: PacketType = receive_packet(stream, machine.protocol_state(), Ok).await?;
+        let result: TunnelResult<Option<SecureChannel>> = machine.handle(packet);
+        for packet in machine.take_outgoing() {
+            send_packet(stream, &packet).await?;
+        }
+        if let Some(channel) = result? {
+            return Ok(channel);
+        }
+    }
+}
+
+/// Accept a client
+///
+/// This function will run the handshake over the stream and open a session on it.
+/// A failed handshake is not retried, the caller decides whether to drop the stream.
+///
+/// # Arguments
+/// stream: **S** - The stream to the client<br/>
+/// identity: **&(PublicKey, PrivateKey)** - The long-term keys of the server<br/>
+/// authorized_keys: **Option<&AuthorizedKeys>** - The client keys allowed to connect, any client is accepted if None<br/>
+/// rekey_policy: **RekeyPolicy** - When the server renews its sending keys
+///
+/// # Returns
+/// **TunnelResult<AsyncSession<S>>** - The session with the client or an error if the handshake failed
+pub async fn accept<S: AsyncRead + AsyncWrite + Unpin>(
+    mut stream: S,
+    identity: &(PublicKey, PrivateKey),
+    authorized_keys: Option<&AuthorizedKeys>,
+    rekey_policy: RekeyPolicy,
+) -> TunnelResult<AsyncSession<S>> {
+    let mut machine: ServerHandshake = ServerHandshake::new(identity, authorized_keys);
+    let channel: SecureChannel = handshake(&mut stream, &mut machine).await?;
+
+    Ok(AsyncSession::new(stream, channel, rekey_policy))
+}
@@ -0,0 +1,115 @@
+use std::{fs, path::Path};
+
+use crate::keys_generator::{
+    errors::{KeyError, KeyResult},
+    keys::PublicKey,
+};
+
+/// Keys allowed to connect to the server
+///
+/// This struct is used to remember the fingerprint of every client identity key the server accepts.
+/// The file lists PEM public keys, such as the `.pub` files written by `keygen`, or their `SHA256:` fingerprints, one per line.
+///
+/// # Fields
+/// - **fingerprints** - The fingerprints of the authorized keys
+pub struct AuthorizedKeys {
+    fingerprints: Vec<String>,
+}
+
+impl AuthorizedKeys {
+    /// Parse the authorized keys
+    ///
+    /// This function will read every public key and fingerprint listed, ignoring blank lines and `#` comments
+    ///
+    /// # Arguments
+    /// content: **&str** - The content of the authorized keys file
+    ///
+    /// # Returns
+    /// **KeyResult<AuthorizedKeys>** - The authorized keys or an error if an entry is invalid
+    pub fn parse(content: &str) -> KeyResult<Self> {
+        let mut fingerprints: Vec<String> = Vec::new();
+        let mut pem_block: Option<String> = None;
+
+        for line in content.lines().map(str::trim) {
+            if let Some(block) = pem_block.as_mut() {
+                block.push_str(line);
+                block.push('\n');
+                if line.starts_with("-----END ") {
+                    fingerprints.push(PublicKey::from_pem(block)?.fingerprint());
+                    pem_block = None;
+                }
+            } else if line.starts_with("-----BEGIN ") {
+                pem_block = Some(format!("{}\n", line));
+            } else if line.starts_with("SHA256:") {
+                let fingerprint: &str = line.split_whitespace().next().unwrap_or(line);
+                fingerprints.push(fingerprint.to_string());
+            } else if !line.is_empty() && !line.starts_with('#') {
+                return Err(KeyError::InvalidEncoding);
+            }
+        }
+        if pem_block.is_some() {
+            return Err(KeyError::InvalidEncoding);
+        }
+        return Ok(AuthorizedKeys { fingerprints });
+    }
+
+    /// Load the authorized keys
+    ///
+    /// This function will read and parse the authorized keys file
+    ///
+    /// # Arguments
+    /// path: **&Path** - The authorized keys file
+    ///
+    /// # Returns
+    /// **KeyResult<AuthorizedKeys>** - The authorized keys or an error if the file could not be read or parsed
+    pub fn load(path: &Path) -> KeyResult<Self> {
+        AuthorizedKeys::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Get the number of authorized keys
+    ///
+    /// # Returns
+    /// **usize** - The number of keys listed
+    pub fn count(self: &Self) -> usize {
+        self.fingerprints.len()
+    }
+
+    /// Check if a key is authorized
+    ///
+    /// This function will look for the fingerprint of the key among the authorized ones
+    ///
+    /// # Arguments
+    /// key: **&PublicKey** - The identity key of the client
+    ///
+    /// # Returns
+    /// **bool** - True if the key is authorized
+    pub fn is_authorized(self: &Self, key: &PublicKey) -> bool {
+        let fingerprint: String = key.fingerprint();
+
+        self.fingerprints.contains(&fingerprint)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys_generator::{encoding::KeyFormat, test_keys::OPENSSL_PKCS8_PUBLIC};
+    use num_bigint::BigUint;
+
+    #[test]
+    fn test_authorized_keys() {
+        let key: PublicKey = PublicKey::from_pem(OPENSSL_PKCS8_PUBLIC).unwrap();
+        let other_key: PublicKey = PublicKey::new(&BigUint::from(7u32), &BigUint::from(3233u32));
+        let third_key: PublicKey = PublicKey::new(&BigUint::from(5u32), &BigUint::from(3233u32));
+        let pem: String = key.to_pem(KeyFormat::Pkcs8);
+        let content: String = format!("# laptop\n{}\n{} desktop\n", pem, other_key.fingerprint());
+        let authorized_keys: AuthorizedKeys = AuthorizedKeys::parse(&content).unwrap();
+
+        assert_eq!(authorized_keys.count(), 2);
+        assert!(authorized_keys.is_authorized(&key));
+        assert!(authorized_keys.is_authorized(&other_key));
+        assert!(!authorized_keys.is_authorized(&third_key));
+        assert!(AuthorizedKeys::parse("not a key\n").is_err());
+        assert!(AuthorizedKeys::parse(&pem[..pem.len() - 20]).is_err());
+    }
+}
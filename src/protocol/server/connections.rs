@@ -0,0 +1,102 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Limit on the number of clients served at the same time
+///
+/// # Fields
+/// - **active** - The number of clients being served<br/>
+/// - **max_clients** - The number of clients that can be served at the same time
+pub struct ConnectionLimit {
+    active: AtomicUsize,
+    max_clients: usize,
+}
+
+impl ConnectionLimit {
+    /// Create a new connection limit
+    ///
+    /// # Arguments
+    /// max_clients: **usize** - The number of clients that can be served at the same time
+    ///
+    /// # Returns
+    /// **ConnectionLimit** - The limit created, with no client served
+    pub fn new(max_clients: usize) -> Self {
+        return ConnectionLimit {
+            active: AtomicUsize::new(0),
+            max_clients,
+        };
+    }
+
+    /// Get the maximum number of clients
+    ///
+    /// # Returns
+    /// **usize** - The number of clients that can be served at the same time
+    pub fn max_clients(self: &Self) -> usize {
+        self.max_clients
+    }
+
+    /// Get the number of clients served
+    ///
+    /// # Returns
+    /// **usize** - The number of slots currently taken
+    pub fn active(self: &Self) -> usize {
+        self.active.load(Ordering::SeqCst)
+    }
+
+    /// Take a slot for a new client
+    ///
+    /// This function will count the client in, unless the limit is reached.
+    /// The slot is given back when it is dropped, however the session ends.
+    ///
+    /// # Returns
+    /// **Option<ConnectionSlot>** - The slot of the client, None if the limit is reached
+    pub fn acquire(self: &Self) -> Option<ConnectionSlot<'_>> {
+        self.active
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |active| {
+                if active < self.max_clients {
+                    Some(active + 1)
+                } else {
+                    None
+                }
+            })
+            .ok()
+            .map(|_| ConnectionSlot { limit: self })
+    }
+}
+
+/// Place of a client within the connection limit
+///
+/// # Fields
+/// - **limit** - The limit the slot was taken from
+pub struct ConnectionSlot<'a> {
+    limit: &'a ConnectionLimit,
+}
+
+impl Drop for ConnectionSlot<'_> {
+    fn drop(&mut self) {
+        self.limit.active.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_connection_limit() {
+        let limit: ConnectionLimit = ConnectionLimit::new(2);
+        let first: Option<ConnectionSlot> = limit.acquire();
+        let second: Option<ConnectionSlot> = limit.acquire();
+
+        assert!(first.is_some() && second.is_some());
+        assert_eq!(limit.active(), 2);
+        assert!(limit.acquire().is_none());
+        assert_eq!(limit.active(), 2);
+        drop(first);
+        assert_eq!(limit.active(), 1);
+        let third: Option<ConnectionSlot> = limit.acquire();
+        assert!(third.is_some());
+        drop(second);
+        drop(third);
+        assert_eq!(limit.active(), 0);
+        assert!(ConnectionLimit::new(0).acquire().is_none());
+    }
+}
@@ -1,3 +1,8 @@
+use crate::{
+    cypher::CypherError,
+    protocol::shared::{dispatch::ProtocolState, errors::ProtocolError},
+};
+
 /// Errors that can occur during the tunneling process.
 ///
 /// This enum is used to represent the different errors that can occur during the tunneling process.
@@ -6,6 +11,15 @@
 /// - **InvalidData** - The data received is invalid
 /// - **InvalidKeySize** - The master key size is invalid
 /// - **HandshakeWentWrong** - The handshake went wrong
+/// - **ClientDisconnected** - The client closed the session or left
+/// - **ConnectionLost** - The connection to the client was lost without the client closing the session, holding the cause
+/// - **TamperedData** - An encrypted message was modified or forged
+/// - **ClientAuthenticationFailed** - The client could not prove it owns an authorized key, holding the reason
+/// - **ReplayedMessage** - A message was replayed, dropped or reordered on its way from the client
+/// - **UnsupportedVersion** - The client only speaks a protocol version that is not supported, holding it
+/// - **NoCommonCipherSuite** - No cipher suite is supported by both sides
+/// - **UnexpectedPacket** - The client sent a packet the server was not waiting for, holding the state of the server and the name of the packet
+/// - **SigningFailed** - The identity key of the server could not sign the handshake, holding the cause
 #[derive(Debug)]
 pub enum TunnelError {
     InvalidData,
@@ -13,6 +27,80 @@ pub enum TunnelError {
     HandshakeWentWrong,
 
     ClientDisconnected,
+    ConnectionLost(ProtocolError),
+    TamperedData,
+    ClientAuthenticationFailed(String),
+    ReplayedMessage,
+    UnsupportedVersion(u16),
+    NoCommonCipherSuite,
+    UnexpectedPacket(ProtocolState, &'static str),
+    SigningFailed(CypherError),
+}
+
+impl std::fmt::Display for TunnelError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TunnelError::InvalidData => write!(f, "invalid data received"),
+            TunnelError::InvalidKeySize => write!(f, "key confirmation of invalid size"),
+            TunnelError::HandshakeWentWrong => write!(f, "handshake went wrong"),
+            TunnelError::ClientDisconnected => write!(f, "client disconnected"),
+            TunnelError::ConnectionLost(_) => write!(f, "connection to the client lost"),
+            TunnelError::TamperedData => write!(f, "message failed authentication"),
+            TunnelError::ClientAuthenticationFailed(reason) => {
+                write!(
+                    f,
+                    "client could not prove it owns an authorized key: {}",
+                    reason
+                )
+            }
+            TunnelError::ReplayedMessage => write!(f, "message replayed, dropped or reordered"),
+            TunnelError::UnsupportedVersion(version) => {
+                write!(f, "protocol version {} is not supported", version)
+            }
+            TunnelError::NoCommonCipherSuite => write!(f, "no cipher suite in common"),
+            TunnelError::UnexpectedPacket(state, packet) => {
+                write!(f, "unexpected {} packet while {}", packet, state)
+            }
+            TunnelError::SigningFailed(_) => write!(f, "could not sign the handshake"),
+        }
+    }
+}
+
+impl std::error::Error for TunnelError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TunnelError::ConnectionLost(err) => Some(err),
+            TunnelError::SigningFailed(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<ProtocolError> for TunnelError {
+    fn from(err: ProtocolError) -> Self {
+        match err {
+            ProtocolError::PeerClosed => TunnelError::ClientDisconnected,
+            err @ (ProtocolError::Disconnected | ProtocolError::Io(_)) => {
+                TunnelError::ConnectionLost(err)
+            }
+            ProtocolError::FrameTooLarge(_) | ProtocolError::InvalidData => {
+                TunnelError::InvalidData
+            }
+            ProtocolError::TamperedData => TunnelError::TamperedData,
+            ProtocolError::OutOfOrder(_, _) => TunnelError::ReplayedMessage,
+            ProtocolError::UnsupportedVersion(version) => TunnelError::UnsupportedVersion(version),
+            ProtocolError::NoCommonCipherSuite => TunnelError::NoCommonCipherSuite,
+            ProtocolError::UnexpectedPacket(state, packet) => {
+                TunnelError::UnexpectedPacket(state, packet)
+            }
+        }
+    }
+}
+
+impl From<CypherError> for TunnelError {
+    fn from(err: CypherError) -> Self {
+        TunnelError::SigningFailed(err)
+    }
 }
 
 /// Result type for the tunneling process.
@@ -1,75 +1,47 @@
-use std::net::TcpStream;
-
 use num_bigint::BigUint;
-use serde::Deserialize;
 
 use crate::{
     keys_generator::keys::PublicKey,
     protocol::{
         server::errors::{TunnelError, TunnelResult},
         shared::{
-            constant::{CLIENT_MASTER_KEY_SIZE, MASTER_KEY_SIZE},
-            types::{HelloClientRequest, KeysValidatedRequest, SharingPubKeyRequest},
+            constant::KEY_CONFIRMATION_SIZE,
+            types::{ClientSignatureRequest, KeysValidatedRequest},
         },
     },
 };
 
-/// Read the client hello message
-///
-/// This function will read the hello message from the client and start the handshake protocol
-///
-/// # Arguments
-/// stream: **&mut TcpStream** - The stream to the client
-///
-/// # Returns
-/// **TunnelResult<[u8; CLIENT_MASTER_KEY_SIZE]>** - The key sent by the client
-pub fn read_client_hello(stream: &mut TcpStream) -> TunnelResult<[u8; CLIENT_MASTER_KEY_SIZE]> {
-    let mut de = serde_json::Deserializer::from_reader(stream);
-    match HelloClientRequest::deserialize(&mut de) {
-        Ok(buffer) => Ok(buffer.key()),
-        Err(_) => Err(TunnelError::InvalidData),
-    }
-}
-
-/// Read the client public key
+/// Get the identity key of the client
 ///
-/// This function will read the public key from the client
+/// This function will decode the identity key the client sent along with its signature of the handshake transcript
 ///
 /// # Arguments
-/// stream: **&mut TcpStream** - The stream to the client
+/// request: **&ClientSignatureRequest** - The signature message received from the client
 ///
 /// # Returns
-/// **TunnelResult<PublicKey>** - The public key of the client
-pub fn read_client_public_key(stream: &mut TcpStream) -> TunnelResult<PublicKey> {
-    let mut de = serde_json::Deserializer::from_reader(stream);
-    match SharingPubKeyRequest::deserialize(&mut de) {
-        Ok(buffer) => Ok(PublicKey::new(
-            &BigUint::from_bytes_be(&buffer.pub_key().0),
-            &BigUint::from_bytes_be(&buffer.pub_key().1),
-        )),
-        Err(_) => Err(TunnelError::InvalidData),
-    }
+/// **Option<PublicKey>** - The identity key of the client, None if it has no identity
+pub fn client_identity(request: &ClientSignatureRequest) -> Option<PublicKey> {
+    request.identity_key().map(|identity_key| {
+        PublicKey::new(
+            &BigUint::from_bytes_be(&identity_key.0),
+            &BigUint::from_bytes_be(&identity_key.1),
+        )
+    })
 }
 
-/// Read the cyphered password
+/// Get the key confirmation
 ///
-/// This function will read the cyphered password from the client
+/// This function will check the value proving the client derived the same secrets has the expected size
 ///
 /// # Arguments
-/// stream: **&mut TcpStream** - The stream to the client
+/// request: **&KeysValidatedRequest** - The key confirmation message received from the client
 ///
 /// # Returns
-/// **TunnelResult<Vec<u8>>** - The cyphered password
-pub fn read_cyphered_password(stream: &mut TcpStream) -> TunnelResult<Vec<u8>> {
-    let mut de = serde_json::Deserializer::from_reader(stream);
-    match KeysValidatedRequest::deserialize(&mut de) {
-        Err(_) => Err(TunnelError::InvalidKeySize),
-        Ok(buffer) => {
-            if buffer.key().len() != MASTER_KEY_SIZE {
-                Err(TunnelError::InvalidKeySize)
-            } else {
-                Ok(buffer.key())
-            }
-        }
+/// **TunnelResult<Vec<u8>>** - The key confirmation or an error if it does not have the expected size
+pub fn key_confirmation(request: &KeysValidatedRequest) -> TunnelResult<Vec<u8>> {
+    if request.key().len() != KEY_CONFIRMATION_SIZE {
+        Err(TunnelError::InvalidKeySize)
+    } else {
+        Ok(request.key())
     }
 }
@@ -1,65 +1,66 @@
-use std::net::TcpStream;
-
-use rand::{rngs::ThreadRng, Rng};
+use rand::RngCore;
 
 use crate::{
-    cypher::enigma,
-    keys_generator::keys::PublicKey,
+    cypher::pss_sign,
+    keys_generator::keys::{PrivateKey, PublicKey},
     protocol::{
         server::errors::TunnelResult,
         shared::{
-            constant::SERVER_MASTER_KEY_SIZE,
-            types::{HelloServerRequest, SharingCryptedPubKeyRequest},
+            constant::{HELLO_RANDOM_SIZE, KEY_SHARE_SIZE},
+            negotiation::CipherSuite,
+            transcript::Transcript,
+            types::{HelloServerRequest, ServerSignatureRequest},
         },
     },
 };
 
-/// Send the hello message to the client
+/// Build the hello message for the client
 ///
-/// This function will send the hello message to the client
+/// This function will build the hello message for the client, carrying the version and the cipher suite selected
 ///
 /// # Arguments
-/// stream: **&mut TcpStream** - The stream to the client
+/// version: **u16** - The protocol version selected<br/>
+/// cipher_suite: **Option<CipherSuite>** - The cipher suite selected, None if no suite is supported by both sides<br/>
+/// key_share: **[u8; KEY_SHARE_SIZE]** - The ephemeral X25519 public key of the server
 ///
 /// # Returns
-/// **TunnelResult<[u8; SERVER_MASTER_KEY_SIZE]>** - The key sent to the client
-pub fn send_hello(stream: &mut TcpStream) -> TunnelResult<[u8; SERVER_MASTER_KEY_SIZE]> {
-    let mut rng: ThreadRng = rand::thread_rng();
-    let mut data: [u8; SERVER_MASTER_KEY_SIZE] = [0; SERVER_MASTER_KEY_SIZE];
-    data.copy_from_slice(
-        (0..SERVER_MASTER_KEY_SIZE)
-            .map(|_| rng.gen_range(0..255))
-            .collect::<Vec<u8>>()
-            .as_slice(),
-    );
-    let buffer: HelloServerRequest = HelloServerRequest::new(data);
-    serde_json::to_writer(stream, &buffer).expect("Failed to send data to client...");
-    Ok(data)
+/// **HelloServerRequest** - The hello message to send to the client
+pub fn build_hello(
+    version: u16,
+    cipher_suite: Option<CipherSuite>,
+    key_share: [u8; KEY_SHARE_SIZE],
+) -> HelloServerRequest {
+    let mut random: [u8; HELLO_RANDOM_SIZE] = [0; HELLO_RANDOM_SIZE];
+
+    rand::thread_rng().fill_bytes(&mut random);
+    HelloServerRequest::new(
+        version,
+        random,
+        cipher_suite.as_ref().map(CipherSuite::code),
+        key_share,
+    )
 }
 
-/// Send the crypted public key to the client
+/// Build the signature of the handshake for the client
 ///
-/// This function will cypher the public key and send it to the client
+/// This function will sign the transcript of the handshake with the identity key of the server so the client can authenticate it
 ///
 /// # Arguments
-/// stream: **&mut TcpStream** - The stream to the client<br/>
-/// pub_key: **&PublicKey** - The public key to cypher<br/>
-/// other_pub_key: **&PublicKey** - The public key of the client
+/// identity: **&(PublicKey, PrivateKey)** - The long-term keys of the server<br/>
+/// transcript: **&Transcript** - The transcript of the handshake so far
 ///
 /// # Returns
-/// **TunnelResult<()>** - An error if the data could not be sent
-pub fn send_crypted_public_key(
-    stream: &mut TcpStream,
-    pub_key: &PublicKey,
-    other_pub_key: &PublicKey,
-) -> TunnelResult<()> {
-    let data: Vec<u8> = serde_json::to_vec(pub_key).unwrap();
-    let data: Vec<u8> = enigma(
-        &data,
-        &other_pub_key.encryption_value(),
-        &other_pub_key.modulus(),
-    );
-    let buffer: SharingCryptedPubKeyRequest = SharingCryptedPubKeyRequest::new(data);
-    serde_json::to_writer(stream, &buffer).expect("Failed to send data to client...");
-    Ok(())
+/// **TunnelResult<ServerSignatureRequest>** - The proof to send to the client or an error if the identity key can not sign
+pub fn build_server_signature(
+    identity: &(PublicKey, PrivateKey),
+    transcript: &Transcript,
+) -> TunnelResult<ServerSignatureRequest> {
+    let signature: Vec<u8> = pss_sign(&transcript.digest(), &identity.1)?;
+    Ok(ServerSignatureRequest::new(
+        (
+            identity.0.encryption_value().to_bytes_be(),
+            identity.0.modulus().to_bytes_be(),
+        ),
+        signature,
+    ))
 }
@@ -0,0 +1,367 @@
+//! State machine of the server side of the handshake
+//!
+//! The machine never touches the network: it is fed the packets received and queues the packets to send,
+//! so it can be driven by any transport and tested on its own. A client hello restarts the handshake from
+//! a clean state whatever the current one, until the handshake is established.
+
+use crate::{
+    cypher::pss_verify,
+    keys_generator::keys::{PrivateKey, PublicKey},
+    protocol::{
+        server::{
+            authorized_keys::AuthorizedKeys,
+            errors::{TunnelError, TunnelResult},
+        },
+        shared::{
+            channel::{Role, SecureChannel},
+            constant::{KO_BYTES, OK_BYTES, PROTOCOL_VERSION},
+            dispatch::ProtocolState,
+            errors::ProtocolResult,
+            key_exchange::{EphemeralKey, MasterKey},
+            key_schedule::KeySchedule,
+            negotiation::{select_cipher_suite, select_version, CipherSuite},
+            transcript::Transcript,
+            types::{
+                ClientSignatureRequest, HandshakeValidatedRequest, HelloClientRequest,
+                HelloServerRequest, KeysValidatedRequest, PacketType, ServerSignatureRequest,
+            },
+        },
+    },
+};
+
+use super::{
+    receive::{client_identity, key_confirmation},
+    send::{build_hello, build_server_signature},
+};
+
+/// State of the server side of the handshake
+///
+/// # Variants
+/// - **AwaitingClientHello** - The handshake has not started or failed
+/// - **AwaitingClientSignature** - The hello and the signature were sent, holding the master key and the transcript
+/// - **AwaitingKeyConfirmation** - The client signature was checked, holding the secrets of the session and the outcome of the authentication
+/// - **Established** - The client proved it derived the same secrets
+enum ServerState {
+    AwaitingClientHello,
+    AwaitingClientSignature {
+        master_key: MasterKey,
+        transcript: Transcript,
+    },
+    AwaitingKeyConfirmation {
+        schedule: KeySchedule,
+        authentication: Result<(), String>,
+    },
+    Established,
+}
+
+impl ServerState {
+    /// Get what the state is waiting for
+    ///
+    /// # Returns
+    /// **ProtocolState** - The state as reported to the dispatcher
+    fn protocol_state(self: &Self) -> ProtocolState {
+        match self {
+            ServerState::AwaitingClientHello => ProtocolState::AwaitingClientHello,
+            ServerState::AwaitingClientSignature { .. } => ProtocolState::AwaitingClientSignature,
+            ServerState::AwaitingKeyConfirmation { .. } => ProtocolState::AwaitingKeyConfirmation,
+            ServerState::Established => ProtocolState::Established,
+        }
+    }
+}
+
+/// Negotiate the parameters of the session
+///
+/// This function will select the protocol version and the cipher suite from the ones offered by the client
+///
+/// # Arguments
+/// client_hello: **&HelloClientRequest** - The hello message received from the client
+///
+/// # Returns
+/// **ProtocolResult<(u16, CipherSuite)>** - The version and the cipher suite of the session or an error if the client offered none supported
+fn negotiate(client_hello: &HelloClientRequest) -> ProtocolResult<(u16, CipherSuite)> {
+    let version: u16 = select_version(client_hello.version())?;
+    let cipher_suite: CipherSuite = select_cipher_suite(client_hello.cipher_suites())?;
+
+    Ok((version, cipher_suite))
+}
+
+/// Authenticate the client
+///
+/// This function will check that the client proved it owns one of the authorized keys
+///
+/// # Arguments
+/// authorized_keys: **&AuthorizedKeys** - The keys allowed to connect<br/>
+/// client_identity: **Option<&PublicKey>** - The identity key sent by the client<br/>
+/// signature: **&[u8]** - The signature of the handshake transcript sent by the client<br/>
+/// transcript: **&Transcript** - The transcript of the handshake, ending with the identity key of the client
+///
+/// # Returns
+/// **Result<(), String>** - The reason the client is rejected, if it is
+fn authenticate_client(
+    authorized_keys: &AuthorizedKeys,
+    client_identity: Option<&PublicKey>,
+    signature: &[u8],
+    transcript: &Transcript,
+) -> Result<(), String> {
+    let client_identity: &PublicKey = match client_identity {
+        None => return Err(String::from("no identity key presented")),
+        Some(client_identity) => client_identity,
+    };
+
+    if !authorized_keys.is_authorized(client_identity) {
+        return Err(format!(
+            "key {} is not authorized",
+            client_identity.fingerprint()
+        ));
+    }
+    if !pss_verify(&transcript.digest(), signature, client_identity) {
+        return Err(format!(
+            "invalid signature for key {}",
+            client_identity.fingerprint()
+        ));
+    }
+    Ok(())
+}
+
+/// Build the result of the handshake for the client
+///
+/// # Arguments
+/// succeed: **bool** - Whether the handshake succeed
+///
+/// # Returns
+/// **PacketType** - The packet telling the client whether the handshake succeed
+fn handshake_result(succeed: bool) -> PacketType {
+    let mut data: [u8; 2] = [0; 2];
+
+    if succeed {
+        data.copy_from_slice(OK_BYTES);
+    } else {
+        data.copy_from_slice(KO_BYTES);
+    }
+    PacketType::HANDSHAKEVALIDATED(HandshakeValidatedRequest::new(data))
+}
+
+/// Server side of the handshake
+///
+/// # Fields
+/// - **state** - The current state of the handshake<br/>
+/// - **outgoing** - The packets waiting to be sent to the client<br/>
+/// - **identity** - The long-term keys used to sign the handshake<br/>
+/// - **authorized_keys** - The client keys allowed to connect, any client is accepted if None
+pub struct ServerHandshake<'a> {
+    state: ServerState,
+    outgoing: Vec<PacketType>,
+    identity: &'a (PublicKey, PrivateKey),
+    authorized_keys: Option<&'a AuthorizedKeys>,
+}
+
+impl<'a> ServerHandshake<'a> {
+    /// Create a new server handshake
+    ///
+    /// This function will create a handshake waiting for the hello of the client
+    ///
+    /// # Arguments
+    /// identity: **&(PublicKey, PrivateKey)** - The long-term keys used to sign the handshake<br/>
+    /// authorized_keys: **Option<&AuthorizedKeys>** - The client keys allowed to connect, any client is accepted if None
+    ///
+    /// # Returns
+    /// **ServerHandshake** - The handshake created
+    pub fn new(
+        identity: &'a (PublicKey, PrivateKey),
+        authorized_keys: Option<&'a AuthorizedKeys>,
+    ) -> Self {
+        return ServerHandshake {
+            state: ServerState::AwaitingClientHello,
+            outgoing: Vec::new(),
+            identity,
+            authorized_keys,
+        };
+    }
+
+    /// Get what the handshake is waiting for
+    ///
+    /// # Returns
+    /// **ProtocolState** - The current state of the handshake
+    pub fn protocol_state(self: &Self) -> ProtocolState {
+        self.state.protocol_state()
+    }
+
+    /// Take the packets to send
+    ///
+    /// # Returns
+    /// **Vec<PacketType>** - The packets queued since the last call, in the order they must be sent
+    pub fn take_outgoing(self: &mut Self) -> Vec<PacketType> {
+        std::mem::take(&mut self.outgoing)
+    }
+
+    /// Handle a packet from the client
+    ///
+    /// This function will move the handshake to its next state if the packet is the one expected.
+    /// Any error brings the handshake back to waiting for a hello, and the packets queued tell the client about it when they can.
+    ///
+    /// # Arguments
+    /// packet: **PacketType** - The packet received
+    ///
+    /// # Returns
+    /// **TunnelResult<Option<SecureChannel>>** - The encrypted channel once the client confirmed the secrets,
+    /// None while it goes on, or an error if the packet is unexpected or the handshake failed
+    pub fn handle(self: &mut Self, packet: PacketType) -> TunnelResult<Option<SecureChannel>> {
+        match (
+            std::mem::replace(&mut self.state, ServerState::AwaitingClientHello),
+            packet,
+        ) {
+            (ServerState::Established, packet) => {
+                self.state = ServerState::Established;
+                Err(TunnelError::UnexpectedPacket(
+                    ProtocolState::Established,
+                    packet.name(),
+                ))
+            }
+            (_, PacketType::HELLOCLIENT(client_hello)) => self.accept_hello(client_hello),
+            (
+                ServerState::AwaitingClientSignature {
+                    master_key,
+                    transcript,
+                },
+                PacketType::CLIENTSIGNATURE(client_signature),
+            ) => self.accept_client_signature(master_key, transcript, client_signature),
+            (
+                ServerState::AwaitingKeyConfirmation {
+                    schedule,
+                    authentication,
+                },
+                PacketType::KEYSVALIDATED(confirmation),
+            ) => self.accept_key_confirmation(schedule, authentication, confirmation),
+            (state, packet) => Err(TunnelError::UnexpectedPacket(
+                state.protocol_state(),
+                packet.name(),
+            )),
+        }
+    }
+
+    /// Accept the hello of the client
+    ///
+    /// This function will negotiate the session, then queue the hello and the signature of the server.
+    /// If the client offered no version or cipher suite supported, the hello tells it so and the handshake stops.
+    ///
+    /// # Arguments
+    /// client_hello: **HelloClientRequest** - The hello received from the client
+    ///
+    /// # Returns
+    /// **TunnelResult<Option<SecureChannel>>** - None or an error if the session could not be negotiated
+    fn accept_hello(
+        self: &mut Self,
+        client_hello: HelloClientRequest,
+    ) -> TunnelResult<Option<SecureChannel>> {
+        let ephemeral_key: EphemeralKey = EphemeralKey::new();
+        let negotiation: ProtocolResult<(u16, CipherSuite)> = negotiate(&client_hello);
+        let (version, cipher_suite): (u16, Option<CipherSuite>) = match &negotiation {
+            Ok((version, cipher_suite)) => (*version, Some(*cipher_suite)),
+            Err(_) => (PROTOCOL_VERSION, None),
+        };
+        let server_hello: HelloServerRequest =
+            build_hello(version, cipher_suite, ephemeral_key.share());
+
+        self.outgoing.clear();
+        if let Err(err) = negotiation {
+            self.outgoing.push(PacketType::HELLOSERVER(server_hello));
+            return Err(err.into());
+        }
+        let master_key: MasterKey = ephemeral_key.agree(&client_hello.key_share())?;
+        let mut transcript: Transcript = Transcript::new();
+        transcript.update_packet(&PacketType::HELLOCLIENT(client_hello));
+        transcript.update_packet(&PacketType::HELLOSERVER(server_hello.clone()));
+        transcript.update_key(&self.identity.0);
+        let server_signature: ServerSignatureRequest =
+            build_server_signature(self.identity, &transcript)?;
+        transcript.update_packet(&PacketType::SERVERSIGNATURE(server_signature.clone()));
+
+        self.outgoing.push(PacketType::HELLOSERVER(server_hello));
+        self.outgoing
+            .push(PacketType::SERVERSIGNATURE(server_signature));
+        self.state = ServerState::AwaitingClientSignature {
+            master_key,
+            transcript,
+        };
+        Ok(None)
+    }
+
+    /// Accept the signature of the client
+    ///
+    /// This function will authenticate the client when authorized keys are set and run the key schedule.
+    /// The outcome of the authentication is only told along with the result of the handshake.
+    ///
+    /// # Arguments
+    /// master_key: **MasterKey** - The master key agreed with the client<br/>
+    /// transcript: **Transcript** - The transcript of the handshake so far<br/>
+    /// client_signature: **ClientSignatureRequest** - The signature received from the client
+    ///
+    /// # Returns
+    /// **TunnelResult<Option<SecureChannel>>** - None
+    fn accept_client_signature(
+        self: &mut Self,
+        master_key: MasterKey,
+        mut transcript: Transcript,
+        client_signature: ClientSignatureRequest,
+    ) -> TunnelResult<Option<SecureChannel>> {
+        let client_identity: Option<PublicKey> = client_identity(&client_signature);
+
+        if let Some(client_identity) = &client_identity {
+            transcript.update_key(client_identity);
+        }
+        let authentication: Result<(), String> = match self.authorized_keys {
+            None => Ok(()),
+            Some(authorized_keys) => authenticate_client(
+                authorized_keys,
+                client_identity.as_ref(),
+                &client_signature.signature(),
+                &transcript,
+            ),
+        };
+        transcript.update_packet(&PacketType::CLIENTSIGNATURE(client_signature));
+
+        self.state = ServerState::AwaitingKeyConfirmation {
+            schedule: KeySchedule::new(&master_key, &transcript.digest()),
+            authentication,
+        };
+        Ok(None)
+    }
+
+    /// Accept the key confirmation of the client
+    ///
+    /// This function will compare, in constant time, the key confirmation received with the expected one
+    /// and queue the result of the handshake
+    ///
+    /// # Arguments
+    /// schedule: **KeySchedule** - The secrets derived by the server<br/>
+    /// authentication: **Result<(), String>** - The outcome of the authentication of the client<br/>
+    /// confirmation: **KeysValidatedRequest** - The key confirmation received from the client
+    ///
+    /// # Returns
+    /// **TunnelResult<Option<SecureChannel>>** - The encrypted channel or an error if the client was refused
+    fn accept_key_confirmation(
+        self: &mut Self,
+        schedule: KeySchedule,
+        authentication: Result<(), String>,
+        confirmation: KeysValidatedRequest,
+    ) -> TunnelResult<Option<SecureChannel>> {
+        let confirmation: Vec<u8> = match key_confirmation(&confirmation) {
+            Ok(confirmation) => confirmation,
+            Err(err) => {
+                self.outgoing.push(handshake_result(false));
+                return Err(err);
+            }
+        };
+        let succeed: bool = schedule.confirmation_matches(&confirmation) && authentication.is_ok();
+
+        self.outgoing.push(handshake_result(succeed));
+        if succeed {
+            self.state = ServerState::Established;
+            return Ok(Some(SecureChannel::new(&schedule, Role::Server)));
+        } else if let Err(reason) = authentication {
+            return Err(TunnelError::ClientAuthenticationFailed(reason));
+        } else {
+            return Err(TunnelError::HandshakeWentWrong);
+        }
+    }
+}
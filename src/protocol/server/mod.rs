@@ -1,3 +1,8 @@
-mod errors;
-mod handshake;
+pub mod async_run;
+pub mod authorized_keys;
+mod connections;
+pub mod errors;
+// Kept inside the crate, the tests of each side drive the handshake of the other
+pub(crate) mod handshake;
 pub mod run;
+pub mod tunnel;
@@ -1,102 +1,132 @@
 use std::{
-    io::{self, Read, Write},
-    net::{TcpListener, TcpStream},
+    net::{Shutdown, TcpListener, TcpStream},
+    path::{Path, PathBuf},
+    thread,
+    time::Duration,
 };
 
 use crate::{
-    cypher::enigma,
-    keys_generator::keys::{PrivateKey, PublicKey},
+    keys_generator::{
+        encoding::{public_key_path, KeyFormat},
+        keys::{generate_keys, KeyOptions, PrivateKey, PublicKey},
+    },
     protocol::{
         server::{
+            authorized_keys::AuthorizedKeys,
+            connections::{ConnectionLimit, ConnectionSlot},
             errors::{TunnelError, TunnelResult},
-            handshake::validate::handshake,
+            handshake::{state::ServerHandshake, validate::handshake},
         },
         shared::{
-            constant::{MAX_CONNECTION_ATTEMPS, MAX_PACKET_SIZE},
+            channel::{RekeyPolicy, SecureChannel},
+            constant::{DEFAULT_MAX_CLIENTS, HANDSHAKE_TIMEOUT_SECONDS, MAX_CONNECTION_ATTEMPS},
+            conversation::{self, ConversationEnd},
+            dispatch::send_packet,
+            errors::ProtocolError,
+            input::UserInput,
             types::PacketType,
         },
     },
 };
 
-/// Send input to the client
-///
-/// This function will read the input from the user and send it to the client
+/// Name a client
 ///
 /// # Arguments
-/// stream: **&mut TcpStream** - The stream to the client<br/>
-/// pub_key: **&PublicKey** - The public key of the client
-fn send_input(stream: &mut TcpStream, pub_key: &PublicKey) {
-    let mut input_buffer: String = String::new();
-    let stdin: io::Stdin = io::stdin();
-    let enigma_buffer: Vec<u8>;
-
-    print!("Localhost: ");
-    std::io::stdout().flush().unwrap();
-    stdin
-        .read_line(&mut input_buffer)
-        .expect("Error while reading standard input...");
-    enigma_buffer = enigma(
-        &input_buffer.as_bytes().to_vec(),
-        &pub_key.encryption_value(),
-        &pub_key.modulus(),
-    );
-    input_buffer = serde_json::to_string(&enigma_buffer).unwrap();
+/// stream: **&TcpStream** - The stream to the client
+///
+/// # Returns
+/// **String** - The address of the client, or a placeholder if it is gone already
+fn peer_name(stream: &TcpStream) -> String {
     stream
-        .write(input_buffer.as_bytes())
-        .expect("Failed sending data to client...");
+        .peer_addr()
+        .map(|address| address.to_string())
+        .unwrap_or_else(|_| String::from("unknown client"))
 }
 
-/// Read the stream from the client
+/// Communicate with the client
 ///
-/// This function will read the stream from the client and print the message
+/// This function will hold the conversation with the client, then tell how the session ended
 ///
 /// # Arguments
 /// stream: **&mut TcpStream** - The stream to the client<br/>
-/// private_key: **&PrivateKey** - The private key to decrypt the message
-fn read_stream(stream: &mut TcpStream, private_key: &PrivateKey) -> TunnelResult<()> {
-    let mut buffer: [u8; MAX_PACKET_SIZE] = [0; MAX_PACKET_SIZE];
-    let json_data: &str;
-    let cyphered_message: Vec<u8>;
-    let mut plain_message: Vec<u8>;
-    let data_size: usize;
+/// channel: **SecureChannel** - The channel keyed by the handshake<br/>
+/// rekey_policy: **RekeyPolicy** - When the server renews its sending keys<br/>
+/// input: **&UserInput** - The lines entered by the user
+fn communicate(
+    stream: &mut TcpStream,
+    channel: SecureChannel,
+    rekey_policy: RekeyPolicy,
+    input: &UserInput,
+) {
+    let peer: String = peer_name(stream);
 
-    data_size = stream
-        .read(&mut buffer)
-        .expect("Failed to read from client...");
-    json_data = std::str::from_utf8(&buffer[0..data_size]).unwrap();
-    if data_size == 0 {
-        return Err(TunnelError::ClientDisconnected);
+    match conversation::communicate(stream, channel, rekey_policy, input, &peer) {
+        Ok(ConversationEnd::InputClosed) => (),
+        Ok(ConversationEnd::PeerClosed) => {
+            println!("{}: {}", peer, TunnelError::ClientDisconnected)
+        }
+        // Only a sealed close ends the session, a leave cuts it short like the stream ending
+        Err(err @ ProtocolError::PeerClosed) => {
+            println!("{}: {}", peer, TunnelError::ConnectionLost(err))
+        }
+        Err(err) => println!("{}: {}", peer, TunnelError::from(err)),
     }
-    cyphered_message = serde_json::from_str(json_data).unwrap();
-    plain_message = enigma(
-        &cyphered_message,
-        &private_key.decryption_value(),
-        &private_key.modulus(),
-    );
-    plain_message.pop();
-    println!(
-        "{}: [{}]",
-        stream.peer_addr().unwrap().ip(),
-        std::str::from_utf8(&plain_message.iter().map(|x| *x as u8).collect::<Vec<u8>>()).unwrap()
-    );
-    Ok(())
 }
 
 /// Launch the server
 ///
-/// This function will launch the server and handle the client connection
+/// This function will launch the server and handle the client connection.
+/// It runs on the thread of the client, which owns the handshake and the keys of its session.
+/// Each read of the handshake times out, so a client stalling in it does not hold its slot forever.
 ///
 /// # Arguments
-/// stream: **&mut TcpStream** - The stream to the client
-fn launch(stream: &mut TcpStream) {
-    println!("New client connected!");
+/// stream: **&mut TcpStream** - The stream to the client<br/>
+/// identity: **&(PublicKey, PrivateKey)** - The long-term keys of the server<br/>
+/// authorized_keys: **Option<&AuthorizedKeys>** - The client keys allowed to connect, any client is accepted if None<br/>
+/// rekey_policy: **RekeyPolicy** - When the server renews its sending keys<br/>
+/// input: **&UserInput** - The lines entered by the user
+fn launch(
+    stream: &mut TcpStream,
+    identity: &(PublicKey, PrivateKey),
+    authorized_keys: Option<&AuthorizedKeys>,
+    rekey_policy: RekeyPolicy,
+    input: &UserInput,
+) {
     let mut connection_attemps: u8 = 0;
-    let mut keys: TunnelResult<((PublicKey, PrivateKey), PublicKey)> = handshake(stream);
+    let mut machine: ServerHandshake = ServerHandshake::new(identity, authorized_keys);
+
+    if let Err(err) = stream.set_read_timeout(Some(Duration::from_secs(HANDSHAKE_TIMEOUT_SECONDS)))
+    {
+        println!("Failed to set the handshake timeout: {}", err);
+        return;
+    }
+    let mut keys: TunnelResult<SecureChannel> = handshake(stream, &mut machine);
     connection_attemps += 1;
     while keys.is_err() && connection_attemps <= MAX_CONNECTION_ATTEMPS {
-        println!("Handshake went wrong : {:?}", keys.err().unwrap());
+        match keys.err().unwrap() {
+            TunnelError::UnsupportedVersion(version) => {
+                println!(
+                    "Client only speaks protocol version {}, stopping connection...",
+                    version
+                );
+                return;
+            }
+            TunnelError::NoCommonCipherSuite => {
+                println!("No cipher suite in common with the client, stopping connection...");
+                return;
+            }
+            TunnelError::ClientDisconnected => {
+                println!("Client left during the handshake");
+                return;
+            }
+            TunnelError::ConnectionLost(_) => {
+                println!("Connection to the client lost or timed out during the handshake");
+                return;
+            }
+            err => println!("Handshake went wrong : {}", err),
+        }
         println!("Trying again");
-        keys = handshake(stream);
+        keys = handshake(stream, &mut machine);
         connection_attemps += 1;
     }
     if keys.is_err() {
@@ -104,21 +134,90 @@ fn launch(stream: &mut TcpStream) {
             "Too many failed connection for client {:?}, stopping connection...",
             stream.peer_addr()
         );
-        serde_json::to_writer(stream, &PacketType::LEAVE)
-            .expect("Failed to send data to client...");
+        let _ = send_packet(stream, &PacketType::LEAVE);
         println!("Client disconnected!");
         return;
     }
-    let keys: ((PublicKey, PrivateKey), PublicKey) = keys.unwrap();
-    loop {
-        match read_stream(stream, &keys.0 .1) {
-            Ok(_) => send_input(stream, &keys.1),
-            Err(err) => {
-                println!("{:?}", err);
-                break;
-            }
-        }
+    if let Err(err) = stream.set_read_timeout(None) {
+        println!("Failed to clear the handshake timeout: {}", err);
+        return;
+    }
+    communicate(stream, keys.unwrap(), rekey_policy, input);
+}
+
+/// Serve a client
+///
+/// This function will run the session of a client from its connection to its teardown, then give its slot back
+///
+/// # Arguments
+/// stream: **TcpStream** - The stream to the client<br/>
+/// slot: **ConnectionSlot** - The place of the client within the connection limit<br/>
+/// identity: **&(PublicKey, PrivateKey)** - The long-term keys of the server<br/>
+/// authorized_keys: **Option<&AuthorizedKeys>** - The client keys allowed to connect, any client is accepted if None<br/>
+/// rekey_policy: **RekeyPolicy** - When the server renews its sending keys<br/>
+/// input: **&UserInput** - The lines entered by the user
+fn serve(
+    mut stream: TcpStream,
+    slot: ConnectionSlot,
+    identity: &(PublicKey, PrivateKey),
+    authorized_keys: Option<&AuthorizedKeys>,
+    rekey_policy: RekeyPolicy,
+    input: &UserInput,
+) {
+    let peer: String = peer_name(&stream);
+
+    println!(
+        "===============START COMMUNICATION WITH {}===============",
+        peer
+    );
+    launch(&mut stream, identity, authorized_keys, rekey_policy, input);
+    let _ = stream.shutdown(Shutdown::Both);
+    drop(slot);
+    println!(
+        "===============END OF COMMUNICATION WITH {}===============",
+        peer
+    );
+}
+
+/// Load the identity of the server
+///
+/// This function will load the long-term keys of the server from a file.
+/// If the file does not exist a new identity is generated and saved there, with its public key next to it in a `.pub` file.
+///
+/// # Arguments
+/// path: **Option<&Path>** - The file holding the private key, a throwaway identity is generated if None
+///
+/// # Returns
+/// **(PublicKey, PrivateKey)** - The long-term keys of the server
+fn load_identity(path: Option<&Path>) -> (PublicKey, PrivateKey) {
+    let path: &Path = match path {
+        None => return generate_keys(&KeyOptions::default()),
+        Some(path) => path,
+    };
+    if path.exists() {
+        let private_key: PrivateKey =
+            PrivateKey::load(path).expect("Failed to load the server identity...");
+        let public_key: PublicKey = private_key
+            .public_key()
+            .expect("Server identity has no public exponent...");
+        return (public_key, private_key);
     }
+    let identity: (PublicKey, PrivateKey) = generate_keys(&KeyOptions::default());
+    let public_path: PathBuf = public_key_path(path);
+    identity
+        .1
+        .save(path, KeyFormat::Pkcs8)
+        .expect("Failed to save the server identity...");
+    identity
+        .0
+        .save(&public_path, KeyFormat::Pkcs8)
+        .expect("Failed to save the server public key...");
+    println!(
+        "New server identity saved to {} and {}",
+        path.display(),
+        public_path.display()
+    );
+    identity
 }
 
 /// Start the server
@@ -127,25 +226,70 @@ fn launch(stream: &mut TcpStream) {
 ///
 /// # Arguments
 /// ip: **String** - The ip address to listen to<br/>
-/// port: **u16** - The port to listen to
-///
-/// # Returns
-/// **()** - Nothing
-pub fn start_server(ip: String, port: u16) -> () {
+/// port: **u16** - The port to listen to<br/>
+/// identity: **Option<PathBuf>** - The file holding the long-term private key of the server<br/>
+/// authorized_keys: **Option<PathBuf>** - The file listing the client keys allowed to connect, any client is accepted if None<br/>
+/// rekey_policy: **RekeyPolicy** - When the server renews its sending keys<br/>
+/// max_clients: **Option<usize>** - The number of clients served at the same time, `DEFAULT_MAX_CLIENTS` if None
+pub fn start_server(
+    ip: String,
+    port: u16,
+    identity: Option<PathBuf>,
+    authorized_keys: Option<PathBuf>,
+    rekey_policy: RekeyPolicy,
+    max_clients: Option<usize>,
+) {
     let endpoint: String = format!("{}:{}", ip, port);
     let listener: TcpListener =
         TcpListener::bind(endpoint).expect("Failed to connect to tcp socket!");
+    let identity: (PublicKey, PrivateKey) = load_identity(identity.as_deref());
+    let authorized_keys: Option<AuthorizedKeys> = authorized_keys
+        .map(|path| AuthorizedKeys::load(&path).expect("Failed to load the authorized keys..."));
+    let input: UserInput = UserInput::spawn();
+    let limit: ConnectionLimit = ConnectionLimit::new(max_clients.unwrap_or(DEFAULT_MAX_CLIENTS));
 
     println!("Server launched on port {}!", port);
-    for stream in listener.incoming() {
-        match stream {
-            Ok(mut stream) => {
-                println!("===============START COMMUNICATION=================");
-                launch(&mut stream);
-                println!("===============END OF COMMUNICATION=================");
-            }
+    println!("Server identity fingerprint: {}", identity.0.fingerprint());
+    if let Some(authorized_keys) = &authorized_keys {
+        println!(
+            "Client authentication required, {} authorized keys",
+            authorized_keys.count()
+        );
+    }
+    println!("Serving up to {} clients at once", limit.max_clients());
+    thread::scope(|scope| {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(mut stream) => match limit.acquire() {
+                    Some(slot) => {
+                        println!(
+                            "New client connected! ({}/{})",
+                            limit.active(),
+                            limit.max_clients()
+                        );
+                        scope.spawn(|| {
+                            serve(
+                                stream,
+                                slot,
+                                &identity,
+                                authorized_keys.as_ref(),
+                                rekey_policy,
+                                &input,
+                            )
+                        });
+                    }
+                    None => {
+                        println!(
+                            "Connection limit of {} clients reached, refusing {}",
+                            limit.max_clients(),
+                            peer_name(&stream)
+                        );
+                        let _ = send_packet(&mut stream, &PacketType::LEAVE);
+                    }
+                },
 
-            Err(e) => println!("Couldn't get client: {e:?}"),
+                Err(e) => println!("Couldn't get client: {e:?}"),
+            }
         }
-    }
+    });
 }
@@ -0,0 +1,108 @@
+use std::io::{Read, Write};
+
+use tokio::io::{AsyncRead, AsyncWrite};
+
+use crate::{
+    keys_generator::keys::{PrivateKey, PublicKey},
+    protocol::{
+        server::{
+            async_run,
+            authorized_keys::AuthorizedKeys,
+            errors::TunnelResult,
+            handshake::{state::ServerHandshake, validate::handshake},
+        },
+        shared::{
+            async_session::AsyncSession,
+            channel::{RekeyPolicy, SecureChannel},
+            session::Session,
+        },
+    },
+};
+
+/// Server side of the tunnel
+///
+/// This struct holds what the server needs to accept sessions: its identity and the clients it lets in.
+/// It never touches the network by itself, the caller hands it the stream of each client, and can share it between threads.
+///
+/// # Fields
+/// - **identity** - The long-term keys of the server<br/>
+/// - **authorized_keys** - The client keys allowed to connect, any client is accepted if None<br/>
+/// - **rekey_policy** - When the sessions renew their sending keys
+pub struct Server {
+    identity: (PublicKey, PrivateKey),
+    authorized_keys: Option<AuthorizedKeys>,
+    rekey_policy: RekeyPolicy,
+}
+
+impl Server {
+    /// Create a new server
+    ///
+    /// This function will create a server accepting any client, renewing its keys with the default policy
+    ///
+    /// # Arguments
+    /// identity: **(PublicKey, PrivateKey)** - The long-term keys of the server
+    ///
+    /// # Returns
+    /// **Server** - The server created
+    pub fn new(identity: (PublicKey, PrivateKey)) -> Self {
+        return Server {
+            identity,
+            authorized_keys: None,
+            rekey_policy: RekeyPolicy::default(),
+        };
+    }
+
+    /// Set the authorized keys
+    ///
+    /// # Arguments
+    /// authorized_keys: **AuthorizedKeys** - The only client keys allowed to connect
+    pub fn set_authorized_keys(self: &mut Self, authorized_keys: AuthorizedKeys) {
+        self.authorized_keys = Some(authorized_keys);
+    }
+
+    /// Set the rekey policy
+    ///
+    /// # Arguments
+    /// policy: **RekeyPolicy** - When the sessions renew their sending keys
+    pub fn set_rekey_policy(self: &mut Self, policy: RekeyPolicy) {
+        self.rekey_policy = policy;
+    }
+
+    /// Accept a client
+    ///
+    /// This function will run the handshake over the stream and open a session on it.
+    /// A failed handshake is not retried, the caller decides whether to drop the stream.
+    ///
+    /// # Arguments
+    /// stream: **S** - The stream to the client, such as a `TcpStream` returned by a listener
+    ///
+    /// # Returns
+    /// **TunnelResult<Session<S>>** - The session with the client or an error if the handshake failed
+    pub fn accept<S: Read + Write>(self: &Self, mut stream: S) -> TunnelResult<Session<S>> {
+        let mut machine: ServerHandshake =
+            ServerHandshake::new(&self.identity, self.authorized_keys.as_ref());
+        let channel: SecureChannel = handshake(&mut stream, &mut machine)?;
+
+        Ok(Session::new(stream, channel, self.rekey_policy))
+    }
+
+    /// Accept a client over an asynchronous stream
+    ///
+    /// # Arguments
+    /// stream: **S** - The stream to the client, such as a tokio `TcpStream` returned by a listener
+    ///
+    /// # Returns
+    /// **TunnelResult<AsyncSession<S>>** - The session with the client or an error if the handshake failed
+    pub async fn accept_async<S: AsyncRead + AsyncWrite + Unpin>(
+        self: &Self,
+        stream: S,
+    ) -> TunnelResult<AsyncSession<S>> {
+        async_run::accept(
+            stream,
+            &self.identity,
+            self.authorized_keys.as_ref(),
+            self.rekey_policy,
+        )
+        .await
+    }
+}
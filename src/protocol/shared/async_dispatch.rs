@@ -0,0 +1,132 @@
+//! Asynchronous counterpart of the framing and the typed dispatch
+//!
+//! The frames and the packets are the same as on a blocking stream, so both kinds of peers talk to each other.
+//! Every function is generic over the tokio I/O traits and awaits instead of blocking the thread,
+//! the encoding, the checks and the routing being the ones of the blocking transport.
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use super::{
+    constant::FRAME_HEADER_SIZE,
+    dispatch::{route_packet, ProtocolState},
+    errors::ProtocolResult,
+    frame::{decode_frame_header, decode_message, encode_frame, encode_message},
+    types::PacketType,
+};
+
+/// Write a frame to the stream
+///
+/// This function will prefix the payload with its length and write the whole frame to the stream
+///
+/// # Arguments
+/// stream: **&mut W** - The stream to write to<br/>
+/// payload: **&[u8]** - The data to send
+///
+/// # Returns
+/// **ProtocolResult<()>** - An error if the payload is too large or the stream failed
+pub async fn write_frame<W: AsyncWrite + Unpin>(
+    stream: &mut W,
+    payload: &[u8],
+) -> ProtocolResult<()> {
+    stream.write_all(&encode_frame(payload)?).await?;
+    stream.flush().await?;
+    Ok(())
+}
+
+/// Read a frame from the stream
+///
+/// This function will wait until a whole frame has been received, whatever the way the transport split it
+///
+/// # Arguments
+/// stream: **&mut R** - The stream to read from
+///
+/// # Returns
+/// **ProtocolResult<Vec<u8>>** - The payload of the frame or an error if the peer left or announced a frame too large
+pub async fn read_frame<R: AsyncRead + Unpin>(stream: &mut R) -> ProtocolResult<Vec<u8>> {
+    let mut header: [u8; FRAME_HEADER_SIZE] = [0; FRAME_HEADER_SIZE];
+    stream.read_exact(&mut header).await?;
+    let mut payload: Vec<u8> = vec![0; decode_frame_header(header)?];
+
+    stream.read_exact(&mut payload).await?;
+    Ok(payload)
+}
+
+/// Send a packet
+///
+/// This function will serialize the packet in its envelope and send it as a single frame
+///
+/// # Arguments
+/// stream: **&mut W** - The stream to write to<br/>
+/// packet: **&PacketType** - The packet to send
+///
+/// # Returns
+/// **ProtocolResult<()>** - An error if the packet could not be sent
+pub async fn send_packet<W: AsyncWrite + Unpin>(
+    stream: &mut W,
+    packet: &PacketType,
+) -> ProtocolResult<()> {
+    write_frame(stream, &encode_message(packet)?).await
+}
+
+/// Receive the packet expected in a state
+///
+/// This function will read the next packet and route it, see `route_packet`
+///
+/// # Arguments
+/// stream: **&mut R** - The stream to read from<br/>
+/// state: **ProtocolState** - What the side is waiting for<br/>
+/// route: **F** - The function keeping the variants expected in the state and giving back any other packet
+///
+/// # Returns
+/// **ProtocolResult<T>** - The value the route extracted or an error if the peer left, the connection was lost
+/// or the peer sent an unexpected packet
+pub async fn receive_packet<R, T, F>(
+    stream: &mut R,
+    state: ProtocolState,
+    route: F,
+) -> ProtocolResult<T>
+where
+    R: AsyncRead + Unpin,
+    F: FnOnce(PacketType) -> Result<T, PacketType>,
+{
+    route_packet(decode_message(&read_frame(stream).await?)?, state, route)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::protocol::shared::{dispatch, errors::ProtocolError, types::DataRequest};
+
+    #[tokio::test]
+    async fn test_async_dispatch_interop() {
+        let mut wire: Vec<u8> = vec![];
+        send_packet(&mut wire, &PacketType::DATA(DataRequest::new(0, vec![1])))
+            .await
+            .unwrap();
+        send_packet(&mut wire, &PacketType::LEAVE).await.unwrap();
+        dispatch::send_packet(&mut wire, &PacketType::PING).unwrap();
+
+        // What one transport writes, the other reads
+        let mut stream: Cursor<Vec<u8>> = Cursor::new(wire);
+        let request: DataRequest = dispatch::receive_packet(
+            &mut stream,
+            ProtocolState::Established,
+            |packet| match packet {
+                PacketType::DATA(request) => Ok(request),
+                packet => Err(packet),
+            },
+        )
+        .unwrap();
+        assert_eq!(request.payload(), [1]);
+        assert!(matches!(
+            dispatch::receive_packet(&mut stream, ProtocolState::Established, Ok),
+            Err(ProtocolError::PeerClosed)
+        ));
+        assert!(matches!(
+            receive_packet(&mut stream, ProtocolState::Established, Ok).await,
+            Ok(PacketType::PING)
+        ));
+    }
+}
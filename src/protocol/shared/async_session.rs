@@ -0,0 +1,202 @@
+//! Established session over an asynchronous stream
+//!
+//! The session moves the messages of the application through the encrypted channel.
+//! It splits into a sending and a receiving half, so two tasks can talk and listen at the same time.
+
+use tokio::io::{self, AsyncRead, AsyncWrite, AsyncWriteExt, ReadHalf, WriteHalf};
+
+use super::{
+    async_dispatch::{read_frame, send_packet},
+    channel::{ChannelReceiver, ChannelSender, RekeyPolicy, SecureChannel},
+    errors::ProtocolResult,
+    frame::decode_message,
+    session::{open_packet, seal_message, Incoming},
+    types::PacketType,
+};
+
+/// Send a message through the channel
+///
+/// # Arguments
+/// stream: **&mut W** - The stream to the peer<br/>
+/// sender: **&mut ChannelSender** - The half of the channel used to encrypt the message<br/>
+/// message: **&[u8]** - The message to send
+///
+/// # Returns
+/// **ProtocolResult<()>** - An error if the peer can not be reached
+async fn send_data<W: AsyncWrite + Unpin>(
+    stream: &mut W,
+    sender: &mut ChannelSender,
+    message: &[u8],
+) -> ProtocolResult<()> {
+    for packet in seal_message(sender, message) {
+        send_packet(stream, &packet).await?;
+    }
+    Ok(())
+}
+
+/// Receive a message from the channel
+///
+/// This function will wait for the next message of the peer, going through the rekeys and pings coming before it
+///
+/// # Arguments
+/// stream: **&mut R** - The stream to the peer<br/>
+/// receiver: **&mut ChannelReceiver** - The half of the channel used to decrypt the message
+///
+/// # Returns
+/// **ProtocolResult<Option<Vec<u8>>>** - The message, None once the peer sealed the close of the session,
+/// or an error if it sent something wrong or the session was cut short
+async fn receive_data<R: AsyncRead + Unpin>(
+    stream: &mut R,
+    receiver: &mut ChannelReceiver,
+) -> ProtocolResult<Option<Vec<u8>>> {
+    loop {
+        let packet: PacketType = decode_message(&read_frame(stream).await?)?;
+
+        match open_packet(receiver, packet)? {
+            Incoming::Message(message) => return Ok(Some(message)),
+            Incoming::Closed => return Ok(None),
+            Incoming::Control => continue,
+        }
+    }
+}
+
+/// Established session over an asynchronous stream
+///
+/// # Fields
+/// - **stream** - The stream to the peer<br/>
+/// - **sender** - The half of the channel encrypting the messages sent<br/>
+/// - **receiver** - The half of the channel decrypting the messages received
+pub struct AsyncSession<S> {
+    stream: S,
+    sender: ChannelSender,
+    receiver: ChannelReceiver,
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncSession<S> {
+    /// Create a new session
+    ///
+    /// # Arguments
+    /// stream: **S** - The stream the handshake ran over<br/>
+    /// channel: **SecureChannel** - The channel keyed by the handshake<br/>
+    /// rekey_policy: **RekeyPolicy** - When the sending keys are renewed
+    ///
+    /// # Returns
+    /// **AsyncSession<S>** - The session created
+    pub fn new(stream: S, channel: SecureChannel, rekey_policy: RekeyPolicy) -> Self {
+        let (mut sender, receiver): (ChannelSender, ChannelReceiver) = channel.split();
+
+        sender.set_rekey_policy(rekey_policy);
+        return AsyncSession {
+            stream,
+            sender,
+            receiver,
+        };
+    }
+
+    /// Send a message to the peer
+    ///
+    /// # Arguments
+    /// message: **&[u8]** - The message to send
+    ///
+    /// # Returns
+    /// **ProtocolResult<()>** - An error if the peer can not be reached
+    pub async fn send(self: &mut Self, message: &[u8]) -> ProtocolResult<()> {
+        send_data(&mut self.stream, &mut self.sender, message).await
+    }
+
+    /// Receive a message from the peer
+    ///
+    /// # Returns
+    /// **ProtocolResult<Option<Vec<u8>>>** - The message, None once the peer sealed the close of the session,
+    /// or an error if it sent something wrong or the session was cut short
+    pub async fn receive(self: &mut Self) -> ProtocolResult<Option<Vec<u8>>> {
+        receive_data(&mut self.stream, &mut self.receiver).await
+    }
+
+    /// Close the session
+    ///
+    /// This function will seal the close of the session for the peer and shut the stream down
+    ///
+    /// # Returns
+    /// **ProtocolResult<()>** - An error if the peer can not be reached
+    pub async fn close(mut self: Self) -> ProtocolResult<()> {
+        send_packet(&mut self.stream, &PacketType::CLOSE(self.sender.close())).await?;
+        self.stream.shutdown().await?;
+        Ok(())
+    }
+
+    /// Split the session
+    ///
+    /// # Returns
+    /// **(AsyncSender<WriteHalf<S>>, AsyncReceiver<ReadHalf<S>>)** - The sending and the receiving half of the session
+    pub fn split(self: Self) -> (AsyncSender<WriteHalf<S>>, AsyncReceiver<ReadHalf<S>>) {
+        let (reader, writer): (ReadHalf<S>, WriteHalf<S>) = io::split(self.stream);
+
+        (
+            AsyncSender {
+                stream: writer,
+                sender: self.sender,
+            },
+            AsyncReceiver {
+                stream: reader,
+                receiver: self.receiver,
+            },
+        )
+    }
+}
+
+/// Sending half of a session
+///
+/// # Fields
+/// - **stream** - The stream to the peer<br/>
+/// - **sender** - The half of the channel encrypting the messages sent
+pub struct AsyncSender<W> {
+    stream: W,
+    sender: ChannelSender,
+}
+
+impl<W: AsyncWrite + Unpin> AsyncSender<W> {
+    /// Send a message to the peer
+    ///
+    /// # Arguments
+    /// message: **&[u8]** - The message to send
+    ///
+    /// # Returns
+    /// **ProtocolResult<()>** - An error if the peer can not be reached
+    pub async fn send(self: &mut Self, message: &[u8]) -> ProtocolResult<()> {
+        send_data(&mut self.stream, &mut self.sender, message).await
+    }
+
+    /// Close the session
+    ///
+    /// This function will seal the close of the session for the peer and shut the sending side of the stream down
+    ///
+    /// # Returns
+    /// **ProtocolResult<()>** - An error if the peer can not be reached
+    pub async fn close(mut self: Self) -> ProtocolResult<()> {
+        send_packet(&mut self.stream, &PacketType::CLOSE(self.sender.close())).await?;
+        self.stream.shutdown().await?;
+        Ok(())
+    }
+}
+
+/// Receiving half of a session
+///
+/// # Fields
+/// - **stream** - The stream from the peer<br/>
+/// - **receiver** - The half of the channel decrypting the messages received
+pub struct AsyncReceiver<R> {
+    stream: R,
+    receiver: ChannelReceiver,
+}
+
+impl<R: AsyncRead + Unpin> AsyncReceiver<R> {
+    /// Receive a message from the peer
+    ///
+    /// # Returns
+    /// **ProtocolResult<Option<Vec<u8>>>** - The message, None once the peer sealed the close of the session,
+    /// or an error if it sent something wrong or the session was cut short
+    pub async fn receive(self: &mut Self) -> ProtocolResult<Option<Vec<u8>>> {
+        receive_data(&mut self.stream, &mut self.receiver).await
+    }
+}
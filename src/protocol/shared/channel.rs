@@ -0,0 +1,551 @@
+//! Authenticated encryption of the data exchanged once the handshake succeed
+
+use std::time::{Duration, Instant};
+
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    ChaCha20Poly1305, Key, Nonce,
+};
+
+use super::{
+    constant::{DEFAULT_REKEY_BYTES, DEFAULT_REKEY_SECONDS, NONCE_SIZE},
+    errors::{ProtocolError, ProtocolResult},
+    key_schedule::{KeySchedule, TrafficSecret},
+    types::DataRequest,
+};
+
+/// Side of the tunnel
+///
+/// This enum is used to know which derived key is used for sending and which one for receiving
+///
+/// # Variants
+/// - **Client** - The side that initiated the connection
+/// - **Server** - The side that accepted the connection
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Role {
+    Client,
+    Server,
+}
+
+/// When a channel renews its sending keys
+///
+/// The sending direction is rekeyed before the next message once either limit is reached.
+/// The age of the keys is only checked when a message is sent.
+///
+/// # Fields
+/// - **max_bytes** - The number of plain bytes sent under the same keys<br/>
+/// - **max_age** - The time the same keys are used for
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RekeyPolicy {
+    max_bytes: u64,
+    max_age: Duration,
+}
+
+impl RekeyPolicy {
+    /// Create a new rekey policy
+    ///
+    /// # Arguments
+    /// max_bytes: **u64** - The number of plain bytes sent under the same keys<br/>
+    /// max_age: **Duration** - The time the same keys are used for
+    ///
+    /// # Returns
+    /// **RekeyPolicy** - The policy created
+    pub fn new(max_bytes: u64, max_age: Duration) -> Self {
+        return RekeyPolicy { max_bytes, max_age };
+    }
+
+    /// Get the byte limit
+    ///
+    /// # Returns
+    /// **u64** - The number of plain bytes sent under the same keys
+    pub fn max_bytes(self: &Self) -> u64 {
+        self.max_bytes
+    }
+
+    /// Get the age limit
+    ///
+    /// # Returns
+    /// **Duration** - The time the same keys are used for
+    pub fn max_age(self: &Self) -> Duration {
+        self.max_age
+    }
+}
+
+impl Default for RekeyPolicy {
+    fn default() -> Self {
+        Self::new(
+            DEFAULT_REKEY_BYTES,
+            Duration::from_secs(DEFAULT_REKEY_SECONDS),
+        )
+    }
+}
+
+/// Kind of content sealed, authenticated along with the sequence number so a data message can not pass for a rekey
+///
+/// # Variants
+/// - **Data** - A message of the user
+/// - **Rekey** - The last message sent under the current keys
+/// - **Close** - The last message of the direction, ending the session
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ContentKind {
+    Data = 0,
+    Rekey = 1,
+    Close = 2,
+}
+
+/// One direction of the channel
+///
+/// # Fields
+/// - **secret** - The current generation of the traffic secrets<br/>
+/// - **cipher** - The cipher initialized with the traffic key<br/>
+/// - **sequence** - The number of messages exchanged under the current keys
+struct Direction {
+    secret: TrafficSecret,
+    cipher: ChaCha20Poly1305,
+    sequence: u64,
+}
+
+impl Direction {
+    /// Create a new direction
+    ///
+    /// # Arguments
+    /// secret: **TrafficSecret** - The secrets of the direction
+    ///
+    /// # Returns
+    /// **Direction** - The direction created
+    fn new(secret: TrafficSecret) -> Self {
+        let cipher: ChaCha20Poly1305 = ChaCha20Poly1305::new(Key::from_slice(secret.key()));
+
+        return Direction {
+            secret,
+            cipher,
+            sequence: 0,
+        };
+    }
+
+    /// Build the nonce of the current message
+    ///
+    /// This function will xor the sequence number of the message into the end of the IV, so that no nonce is used twice with a key
+    ///
+    /// # Returns
+    /// **[u8; NONCE_SIZE]** - The nonce of the message
+    fn nonce(self: &Self) -> [u8; NONCE_SIZE] {
+        let mut nonce: [u8; NONCE_SIZE] = *self.secret.iv();
+
+        for (byte, sequence_byte) in nonce[NONCE_SIZE - 8..]
+            .iter_mut()
+            .zip(self.sequence.to_be_bytes())
+        {
+            *byte ^= sequence_byte;
+        }
+        nonce
+    }
+
+    /// Build the additional data of the current message
+    ///
+    /// # Arguments
+    /// kind: **ContentKind** - The kind of content of the message
+    ///
+    /// # Returns
+    /// **[u8; 9]** - The kind of content followed by the sequence number
+    fn additional_data(self: &Self, kind: ContentKind) -> [u8; 9] {
+        let mut aad: [u8; 9] = [kind as u8; 9];
+
+        aad[1..].copy_from_slice(&self.sequence.to_be_bytes());
+        aad
+    }
+
+    /// Switch to the next generation of the traffic secrets
+    ///
+    /// This function will replace the secrets and the cipher, and restart the sequence numbers
+    fn advance(self: &mut Self) {
+        *self = Direction::new(self.secret.next());
+    }
+}
+
+/// Encrypted data channel
+///
+/// This struct holds one ChaCha20-Poly1305 key per direction, both taken from the key schedule.
+/// The ciphers wipe their key when dropped.
+///
+/// Each direction counts its messages. The sequence number of a message builds its nonce and is authenticated
+/// along with it, and the receiver only accepts the next one, so a message can not be replayed, dropped or reordered
+/// without being noticed.
+///
+/// Each side rekeys its sending direction on its own: it seals a rekey message as the last one under the current keys,
+/// then ratchets them. The peer ratchets its receiving direction when it opens that message, so every message sent
+/// before it still opens with the keys it was sealed with.
+///
+/// The session ends with a close message sealed like any other, so the stream being cut can not pass for the end of the session.
+///
+/// As the directions share no state, the channel splits into a sending and a receiving half that can be used from different threads.
+///
+/// # Fields
+/// - **sender** - The half encrypting the messages sent to the peer<br/>
+/// - **receiver** - The half decrypting the messages received from the peer
+pub struct SecureChannel {
+    sender: ChannelSender,
+    receiver: ChannelReceiver,
+}
+
+impl SecureChannel {
+    /// Create a new secure channel
+    ///
+    /// This function will pick the secrets of each direction from the key schedule
+    ///
+    /// # Arguments
+    /// schedule: **&KeySchedule** - The secrets derived at the end of the handshake<br/>
+    /// role: **Role** - The side of the tunnel owning the channel
+    ///
+    /// # Returns
+    /// **SecureChannel** - The channel created
+    pub fn new(schedule: &KeySchedule, role: Role) -> Self {
+        let (sending, receiving): (&TrafficSecret, &TrafficSecret) = match role {
+            Role::Client => (schedule.client_to_server(), schedule.server_to_client()),
+            Role::Server => (schedule.server_to_client(), schedule.client_to_server()),
+        };
+
+        return SecureChannel {
+            sender: ChannelSender {
+                sending: Direction::new(sending.clone()),
+                policy: RekeyPolicy::default(),
+                bytes_since_rekey: 0,
+                last_rekey: Instant::now(),
+            },
+            receiver: ChannelReceiver {
+                receiving: Direction::new(receiving.clone()),
+            },
+        };
+    }
+
+    /// Split the channel
+    ///
+    /// # Returns
+    /// **(ChannelSender, ChannelReceiver)** - The sending and the receiving half of the channel
+    pub fn split(self: Self) -> (ChannelSender, ChannelReceiver) {
+        (self.sender, self.receiver)
+    }
+}
+
+/// Sending half of the encrypted data channel
+///
+/// # Fields
+/// - **sending** - The direction of the messages sent to the peer<br/>
+/// - **policy** - When the sending direction is rekeyed<br/>
+/// - **bytes_since_rekey** - The number of plain bytes sent under the current sending keys<br/>
+/// - **last_rekey** - When the sending keys were taken into use
+pub struct ChannelSender {
+    sending: Direction,
+    policy: RekeyPolicy,
+    bytes_since_rekey: u64,
+    last_rekey: Instant,
+}
+
+impl ChannelSender {
+    /// Set the rekey policy
+    ///
+    /// # Arguments
+    /// policy: **RekeyPolicy** - When the sending direction is rekeyed
+    pub fn set_rekey_policy(self: &mut Self, policy: RekeyPolicy) {
+        self.policy = policy;
+    }
+
+    /// Check if the sending direction must be rekeyed
+    ///
+    /// # Returns
+    /// **bool** - True if the byte or age limit of the policy is reached
+    pub fn needs_rekey(self: &Self) -> bool {
+        self.bytes_since_rekey >= self.policy.max_bytes
+            || self.last_rekey.elapsed() >= self.policy.max_age
+    }
+
+    /// Encrypt a message
+    ///
+    /// This function will encrypt the message under the next sequence number of the sending direction
+    ///
+    /// # Arguments
+    /// message: **&[u8]** - The message to encrypt
+    ///
+    /// # Returns
+    /// **DataRequest** - The encrypted message ready to be sent
+    pub fn seal(self: &mut Self, message: &[u8]) -> DataRequest {
+        self.bytes_since_rekey = self.bytes_since_rekey.saturating_add(message.len() as u64);
+        self.seal_content(ContentKind::Data, message)
+    }
+
+    /// Rekey the sending direction
+    ///
+    /// This function will seal the rekey message under the current keys, then switch to the next generation
+    ///
+    /// # Returns
+    /// **DataRequest** - The rekey message to send before any other message
+    pub fn rekey(self: &mut Self) -> DataRequest {
+        let request: DataRequest = self.seal_content(ContentKind::Rekey, &[]);
+
+        self.sending.advance();
+        self.bytes_since_rekey = 0;
+        self.last_rekey = Instant::now();
+        request
+    }
+
+    /// Close the sending direction
+    ///
+    /// This function will seal the close message, which proves to the peer that nothing was cut from the end of the session
+    ///
+    /// # Returns
+    /// **DataRequest** - The close message to send as the last message
+    pub fn close(self: &mut Self) -> DataRequest {
+        self.seal_content(ContentKind::Close, &[])
+    }
+
+    /// Encrypt a message of any kind
+    ///
+    /// # Arguments
+    /// kind: **ContentKind** - The kind of content of the message<br/>
+    /// message: **&[u8]** - The message to encrypt
+    ///
+    /// # Returns
+    /// **DataRequest** - The encrypted message ready to be sent
+    fn seal_content(self: &mut Self, kind: ContentKind, message: &[u8]) -> DataRequest {
+        let sequence: u64 = self.sending.sequence;
+        let payload: Vec<u8> = self
+            .sending
+            .cipher
+            .encrypt(
+                Nonce::from_slice(&self.sending.nonce()),
+                Payload {
+                    msg: message,
+                    aad: &self.sending.additional_data(kind),
+                },
+            )
+            .expect("Message size is within the ChaCha20-Poly1305 limits");
+
+        self.sending.sequence = sequence
+            .checked_add(1)
+            .expect("Sequence numbers of a channel are exhausted");
+        DataRequest::new(sequence, payload)
+    }
+}
+
+/// Receiving half of the encrypted data channel
+///
+/// # Fields
+/// - **receiving** - The direction of the messages received from the peer
+pub struct ChannelReceiver {
+    receiving: Direction,
+}
+
+impl ChannelReceiver {
+    /// Decrypt a message
+    ///
+    /// This function will check the message is the next one of the receiving direction, then check its authentication tag and decrypt it
+    ///
+    /// # Arguments
+    /// request: **&DataRequest** - The encrypted message received
+    ///
+    /// # Returns
+    /// **ProtocolResult<Vec<u8>>** - The plain message or an error if it was replayed, reordered or tampered with
+    pub fn open(self: &mut Self, request: &DataRequest) -> ProtocolResult<Vec<u8>> {
+        self.open_content(ContentKind::Data, request)
+    }
+
+    /// Accept a rekey from the peer
+    ///
+    /// This function will check the rekey message like any other message, then switch the receiving direction to the next generation
+    ///
+    /// # Arguments
+    /// request: **&DataRequest** - The rekey message received
+    ///
+    /// # Returns
+    /// **ProtocolResult<()>** - An error if the message was replayed, reordered or tampered with
+    pub fn accept_rekey(self: &mut Self, request: &DataRequest) -> ProtocolResult<()> {
+        self.open_content(ContentKind::Rekey, request)?;
+        self.receiving.advance();
+        Ok(())
+    }
+
+    /// Accept the close of the peer
+    ///
+    /// This function will check the close message like any other message
+    ///
+    /// # Arguments
+    /// request: **&DataRequest** - The close message received
+    ///
+    /// # Returns
+    /// **ProtocolResult<()>** - An error if the message was replayed, reordered or tampered with
+    pub fn accept_close(self: &mut Self, request: &DataRequest) -> ProtocolResult<()> {
+        self.open_content(ContentKind::Close, request)?;
+        Ok(())
+    }
+
+    /// Decrypt a message of any kind
+    ///
+    /// # Arguments
+    /// kind: **ContentKind** - The kind of content expected<br/>
+    /// request: **&DataRequest** - The encrypted message received
+    ///
+    /// # Returns
+    /// **ProtocolResult<Vec<u8>>** - The plain message or an error if it was replayed, reordered or tampered with
+    fn open_content(
+        self: &mut Self,
+        kind: ContentKind,
+        request: &DataRequest,
+    ) -> ProtocolResult<Vec<u8>> {
+        let sequence: u64 = request.sequence();
+
+        if sequence != self.receiving.sequence {
+            return Err(ProtocolError::OutOfOrder(self.receiving.sequence, sequence));
+        }
+        let message: Vec<u8> = self
+            .receiving
+            .cipher
+            .decrypt(
+                Nonce::from_slice(&self.receiving.nonce()),
+                Payload {
+                    msg: request.payload(),
+                    aad: &self.receiving.additional_data(kind),
+                },
+            )
+            .map_err(|_| ProtocolError::TamperedData)?;
+
+        self.receiving.sequence += 1;
+        Ok(message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::shared::{constant::MASTER_KEY_SIZE, transcript::TRANSCRIPT_HASH_SIZE};
+
+    #[test]
+    fn test_channel_round_trip() {
+        let schedule: KeySchedule =
+            KeySchedule::new(&[42; MASTER_KEY_SIZE], &[0; TRANSCRIPT_HASH_SIZE]);
+        let (mut client_sender, mut client_receiver): (ChannelSender, ChannelReceiver) =
+            SecureChannel::new(&schedule, Role::Client).split();
+        let (mut server_sender, mut server_receiver): (ChannelSender, ChannelReceiver) =
+            SecureChannel::new(&schedule, Role::Server).split();
+
+        let request: DataRequest = client_sender.seal(b"Hello server");
+        assert_eq!(server_receiver.open(&request).unwrap(), b"Hello server");
+        let request: DataRequest = server_sender.seal(b"Hello client");
+        assert_eq!(client_receiver.open(&request).unwrap(), b"Hello client");
+        let first: DataRequest = client_sender.seal(b"again");
+        let second: DataRequest = client_sender.seal(b"again");
+        assert_eq!(second.sequence(), first.sequence() + 1);
+        // The same message never gets the same ciphertext twice
+        assert_ne!(first.payload(), second.payload());
+    }
+
+    #[test]
+    fn test_channel_rejects_replay() {
+        let schedule: KeySchedule =
+            KeySchedule::new(&[42; MASTER_KEY_SIZE], &[0; TRANSCRIPT_HASH_SIZE]);
+        let mut client_sender: ChannelSender =
+            SecureChannel::new(&schedule, Role::Client).split().0;
+        let mut server_receiver: ChannelReceiver =
+            SecureChannel::new(&schedule, Role::Server).split().1;
+        let first: DataRequest = client_sender.seal(b"first");
+        let second: DataRequest = client_sender.seal(b"second");
+        let third: DataRequest = client_sender.seal(b"third");
+
+        assert!(matches!(
+            server_receiver.open(&second),
+            Err(ProtocolError::OutOfOrder(0, 1))
+        ));
+        assert_eq!(server_receiver.open(&first).unwrap(), b"first");
+        assert!(matches!(
+            server_receiver.open(&first),
+            Err(ProtocolError::OutOfOrder(1, 0))
+        ));
+        // Rewriting the sequence number breaks the authentication
+        let renumbered: DataRequest = DataRequest::new(1, third.payload().to_vec());
+        assert!(matches!(
+            server_receiver.open(&renumbered),
+            Err(ProtocolError::TamperedData)
+        ));
+        assert_eq!(server_receiver.open(&second).unwrap(), b"second");
+        assert_eq!(server_receiver.open(&third).unwrap(), b"third");
+    }
+
+    #[test]
+    fn test_channel_rejects_tampering() {
+        let schedule: KeySchedule =
+            KeySchedule::new(&[42; MASTER_KEY_SIZE], &[0; TRANSCRIPT_HASH_SIZE]);
+        let (mut client_sender, mut client_receiver): (ChannelSender, ChannelReceiver) =
+            SecureChannel::new(&schedule, Role::Client).split();
+        let mut server_receiver: ChannelReceiver =
+            SecureChannel::new(&schedule, Role::Server).split().1;
+        let request: DataRequest = client_sender.seal(b"Hello server");
+
+        let mut payload: Vec<u8> = request.payload().to_vec();
+        payload[0] ^= 1;
+        let tampered: DataRequest = DataRequest::new(request.sequence(), payload);
+        assert!(matches!(
+            server_receiver.open(&tampered),
+            Err(ProtocolError::TamperedData)
+        ));
+        // A message can not be reflected back to its sender
+        assert!(matches!(
+            client_receiver.open(&request),
+            Err(ProtocolError::TamperedData)
+        ));
+    }
+
+    #[test]
+    fn test_channel_rekey() {
+        let schedule: KeySchedule =
+            KeySchedule::new(&[42; MASTER_KEY_SIZE], &[0; TRANSCRIPT_HASH_SIZE]);
+        let (mut client_sender, mut client_receiver): (ChannelSender, ChannelReceiver) =
+            SecureChannel::new(&schedule, Role::Client).split();
+        let (mut server_sender, mut server_receiver): (ChannelSender, ChannelReceiver) =
+            SecureChannel::new(&schedule, Role::Server).split();
+
+        client_sender.set_rekey_policy(RekeyPolicy::new(8, Duration::from_secs(3600)));
+        assert!(!client_sender.needs_rekey());
+        let before: DataRequest = client_sender.seal(b"sealed before the rekey");
+        assert!(client_sender.needs_rekey());
+        let rekey: DataRequest = client_sender.rekey();
+        assert!(!client_sender.needs_rekey());
+        let after: DataRequest = client_sender.seal(b"sealed after the rekey");
+        assert_eq!(after.sequence(), 0);
+
+        // A rekey message can not pass for data and the other way around
+        assert!(matches!(
+            server_receiver.accept_rekey(&before),
+            Err(ProtocolError::TamperedData)
+        ));
+        // The message in flight still opens with the keys it was sealed with
+        assert_eq!(
+            server_receiver.open(&before).unwrap(),
+            b"sealed before the rekey"
+        );
+        assert!(matches!(
+            server_receiver.open(&after),
+            Err(ProtocolError::OutOfOrder(1, 0))
+        ));
+        assert!(matches!(
+            server_receiver.open(&rekey),
+            Err(ProtocolError::TamperedData)
+        ));
+        server_receiver.accept_rekey(&rekey).unwrap();
+        assert_eq!(
+            server_receiver.open(&after).unwrap(),
+            b"sealed after the rekey"
+        );
+        // Neither can a close pass for a rekey
+        let close: DataRequest = client_sender.close();
+        assert!(matches!(
+            server_receiver.accept_rekey(&close),
+            Err(ProtocolError::TamperedData)
+        ));
+        server_receiver.accept_close(&close).unwrap();
+
+        // The other direction is unaffected
+        let request: DataRequest = server_sender.seal(b"Hello client");
+        assert_eq!(client_receiver.open(&request).unwrap(), b"Hello client");
+        client_sender.set_rekey_policy(RekeyPolicy::new(u64::MAX, Duration::ZERO));
+        assert!(client_sender.needs_rekey());
+    }
+}
@@ -1,19 +1,73 @@
 //! Constants used in the protocol
 
-/// Maximum size of a packet
-pub const MAX_PACKET_SIZE: usize = 1024;
+/// Maximum size of the payload of a frame
+pub const MAX_FRAME_SIZE: usize = 1024 * 1024;
+
+/// Number of bytes used to encode the length of a frame
+pub const FRAME_HEADER_SIZE: usize = 4;
+
+/// Maximum size of the plain data sealed in one message by a session, so its frame stays below `MAX_FRAME_SIZE`
+pub const MAX_MESSAGE_SIZE: usize = 64 * 1024;
+
+/// Highest protocol version spoken
+pub const PROTOCOL_VERSION: u16 = 1;
+
+/// Lowest protocol version still spoken
+pub const MIN_PROTOCOL_VERSION: u16 = 1;
 
 /// Maximum number of connection attempts
 pub const MAX_CONNECTION_ATTEMPS: u8 = 3;
 
-/// Number of byte sent to the server to perform the hello_client during the handshake
-pub const CLIENT_MASTER_KEY_SIZE: usize = 12;
+/// Number of random bytes sent in each hello, making the transcript of every handshake unique
+pub const HELLO_RANDOM_SIZE: usize = 12;
+
+/// Size of the ephemeral X25519 public key sent in each hello
+pub const KEY_SHARE_SIZE: usize = 32;
+
+/// Size of the master key, the secret agreed with X25519
+pub const MASTER_KEY_SIZE: usize = 32;
+
+/// Size of the value proving the client derived the same secrets as the server
+pub const KEY_CONFIRMATION_SIZE: usize = 32;
+
+/// Label used to derive the key confirmation
+pub const KEY_CONFIRMATION_LABEL: &[u8] = "ip-tunnel key confirmation".as_bytes();
+
+/// Size of the keys protecting the data channel
+pub const SESSION_KEY_SIZE: usize = 32;
+
+/// Size of the nonce sent along each encrypted message, and of the traffic IVs it is built from
+pub const NONCE_SIZE: usize = 12;
+
+/// Label used to derive the key protecting the client to server direction
+pub const CLIENT_TO_SERVER_KEY_LABEL: &[u8] = "ip-tunnel client to server key".as_bytes();
+
+/// Label used to derive the IV of the client to server direction
+pub const CLIENT_TO_SERVER_IV_LABEL: &[u8] = "ip-tunnel client to server iv".as_bytes();
+
+/// Label used to derive the key protecting the server to client direction
+pub const SERVER_TO_CLIENT_KEY_LABEL: &[u8] = "ip-tunnel server to client key".as_bytes();
+
+/// Label used to derive the IV of the server to client direction
+pub const SERVER_TO_CLIENT_IV_LABEL: &[u8] = "ip-tunnel server to client iv".as_bytes();
+
+/// Label used to derive the next generation of a traffic key
+pub const KEY_UPDATE_KEY_LABEL: &[u8] = "ip-tunnel key update key".as_bytes();
+
+/// Label used to derive the next generation of a traffic IV
+pub const KEY_UPDATE_IV_LABEL: &[u8] = "ip-tunnel key update iv".as_bytes();
+
+/// Number of bytes sent under the same traffic key before it is renewed, by default
+pub const DEFAULT_REKEY_BYTES: u64 = 1024 * 1024 * 1024;
+
+/// Number of seconds a traffic key is used before it is renewed, by default
+pub const DEFAULT_REKEY_SECONDS: u64 = 3600;
 
-/// Number of byte sent to the client to perform the hello_server during the handshake
-pub const SERVER_MASTER_KEY_SIZE: usize = 12;
+/// Number of seconds the server waits for each message of the handshake before dropping the client
+pub const HANDSHAKE_TIMEOUT_SECONDS: u64 = 30;
 
-/// Size of the master key
-pub const MASTER_KEY_SIZE: usize = CLIENT_MASTER_KEY_SIZE + SERVER_MASTER_KEY_SIZE;
+/// Number of clients the server serves at the same time, by default
+pub const DEFAULT_MAX_CLIENTS: usize = 16;
 
 /// Bytes sent by the server to indicate that the handshake succeed
 pub const OK_BYTES: &[u8] = "OK".as_bytes();
@@ -0,0 +1,146 @@
+//! Conversation of the command line over an established session
+//!
+//! The lines entered by the user are sent to the peer while its messages are printed as they arrive,
+//! so both sides of the tunnel can talk at any time. The client and the server only differ in how they name the peer.
+
+use std::{
+    net::{Shutdown, TcpStream},
+    sync::mpsc::{self, Receiver, Sender},
+    thread::{self, JoinHandle},
+};
+
+use super::{
+    channel::{ChannelReceiver, ChannelSender, RekeyPolicy, SecureChannel},
+    dispatch::send_packet,
+    errors::ProtocolResult,
+    input::{SessionEvent, UserInput},
+    session::{receive_data, send_data},
+    types::PacketType,
+};
+
+/// How a conversation ended
+///
+/// # Variants
+/// - **InputClosed** - The standard input reached its end and the close of the session was sealed for the peer
+/// - **PeerClosed** - The peer sealed the close of the session
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConversationEnd {
+    InputClosed,
+    PeerClosed,
+}
+
+/// Send an input to the peer
+///
+/// This function will send a line entered by the user to the peer cyphered.
+/// An empty line only keeps the session alive.
+///
+/// # Arguments
+/// stream: **&mut TcpStream** - The stream to the peer<br/>
+/// sender: **&mut ChannelSender** - The half of the channel used to encrypt the message<br/>
+/// input: **&str** - The line entered, with its line ending
+///
+/// # Returns
+/// **ProtocolResult<()>** - An error if the peer can not be reached
+fn send_input(
+    stream: &mut TcpStream,
+    sender: &mut ChannelSender,
+    input: &str,
+) -> ProtocolResult<()> {
+    if input.trim_end_matches(['\r', '\n']).is_empty() {
+        return send_packet(stream, &PacketType::PING);
+    }
+    send_data(stream, sender, input.as_bytes())
+}
+
+/// Receive the messages of the peer
+///
+/// This function will print the messages of the peer as they arrive, along with its name,
+/// until the peer seals its close, leaves or sends something wrong
+///
+/// # Arguments
+/// stream: **TcpStream** - The stream to the peer<br/>
+/// receiver: **ChannelReceiver** - The half of the channel used to decrypt the messages<br/>
+/// peer: **String** - The name of the peer printed along with its messages<br/>
+/// events: **Sender<SessionEvent>** - The events of the session, told when the peer is gone
+///
+/// # Returns
+/// **ProtocolResult<()>** - An error if the session was cut short or the peer sent something wrong
+fn receive_messages(
+    mut stream: TcpStream,
+    mut receiver: ChannelReceiver,
+    peer: String,
+    events: Sender<SessionEvent>,
+) -> ProtocolResult<()> {
+    let received: ProtocolResult<()> = loop {
+        match receive_data(&mut stream, &mut receiver) {
+            Ok(Some(mut message)) => {
+                message.pop();
+                println!("{}: [{}]", peer, String::from_utf8_lossy(&message));
+            }
+            Ok(None) => break Ok(()),
+            Err(err) => break Err(err),
+        }
+    };
+    let _ = events.send(SessionEvent::PeerClosed);
+    received
+}
+
+/// Communicate with the peer
+///
+/// This function will receive the messages of the peer on their own thread while sending the lines entered by the user.
+/// The conversation ends when the input reaches its end or the peer is gone.
+///
+/// # Arguments
+/// stream: **&mut TcpStream** - The stream to the peer<br/>
+/// channel: **SecureChannel** - The channel keyed by the handshake<br/>
+/// rekey_policy: **RekeyPolicy** - When the sending keys are renewed<br/>
+/// input: **&UserInput** - The lines entered by the user<br/>
+/// peer: **&str** - The name of the peer printed along with its messages
+///
+/// # Returns
+/// **ProtocolResult<ConversationEnd>** - The side that closed the session, or an error if the session was cut short
+/// or the peer sent something wrong
+pub fn communicate(
+    stream: &mut TcpStream,
+    channel: SecureChannel,
+    rekey_policy: RekeyPolicy,
+    input: &UserInput,
+    peer: &str,
+) -> ProtocolResult<ConversationEnd> {
+    let (mut sender, receiver): (ChannelSender, ChannelReceiver) = channel.split();
+    let (events, session_events): (Sender<SessionEvent>, Receiver<SessionEvent>) = mpsc::channel();
+    let reader_stream: TcpStream = stream.try_clone()?;
+    let reader_peer: String = peer.to_string();
+    let reader_events: Sender<SessionEvent> = events.clone();
+    let reader: JoinHandle<ProtocolResult<()>> = thread::spawn(move || {
+        receive_messages(reader_stream, receiver, reader_peer, reader_events)
+    });
+    let mut sent: Option<ProtocolResult<ConversationEnd>> = None;
+
+    sender.set_rekey_policy(rekey_policy);
+    input.subscribe(events);
+    while let Ok(event) = session_events.recv() {
+        match event {
+            SessionEvent::Input(line) => {
+                if let Err(err) = send_input(stream, &mut sender, &line) {
+                    sent = Some(Err(err));
+                    break;
+                }
+            }
+            SessionEvent::InputClosed => {
+                sent = Some(
+                    send_packet(stream, &PacketType::CLOSE(sender.close()))
+                        .map(|_| ConversationEnd::InputClosed),
+                );
+                break;
+            }
+            SessionEvent::PeerClosed => break,
+        }
+    }
+    let _ = stream.shutdown(Shutdown::Both);
+    let received: ProtocolResult<()> = reader
+        .join()
+        .expect("Failed to stop receiving from the peer...");
+    // Once the sending side stopped, the reader only ends because the stream was shut down
+    sent.unwrap_or_else(|| received.map(|_| ConversationEnd::PeerClosed))
+}
@@ -0,0 +1,166 @@
+//! Typed dispatch of the packets exchanged on the wire
+//!
+//! Every message travels in the `PacketType` envelope. The receiving side states what it is waiting for and
+//! routes the variants it expects, so a packet arriving at the wrong time is reported along with that state.
+
+use std::io::{Read, Write};
+
+use super::{
+    errors::{ProtocolError, ProtocolResult},
+    frame::{receive_message, send_message},
+    types::PacketType,
+};
+
+/// What a side of the tunnel is waiting for
+///
+/// # Variants
+/// - **Idle** - No handshake is running, before it starts or after it failed
+/// - **AwaitingClientHello** - The server waits for the hello of the client
+/// - **AwaitingServerHello** - The client waits for the hello of the server
+/// - **AwaitingServerSignature** - The client waits for the server to prove its identity
+/// - **AwaitingClientSignature** - The server waits for the client to prove its identity
+/// - **AwaitingKeyConfirmation** - The server waits for the client to prove it derived the same secrets
+/// - **AwaitingHandshakeResult** - The client waits for the server to accept or refuse the handshake
+/// - **Established** - The handshake succeeded and data flows on the channel
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProtocolState {
+    Idle,
+    AwaitingClientHello,
+    AwaitingServerHello,
+    AwaitingServerSignature,
+    AwaitingClientSignature,
+    AwaitingKeyConfirmation,
+    AwaitingHandshakeResult,
+    Established,
+}
+
+impl std::fmt::Display for ProtocolState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProtocolState::Idle => write!(f, "not running a handshake"),
+            ProtocolState::AwaitingClientHello => write!(f, "waiting for the client hello"),
+            ProtocolState::AwaitingServerHello => write!(f, "waiting for the server hello"),
+            ProtocolState::AwaitingServerSignature => {
+                write!(f, "waiting for the server signature")
+            }
+            ProtocolState::AwaitingClientSignature => {
+                write!(f, "waiting for the client signature")
+            }
+            ProtocolState::AwaitingKeyConfirmation => {
+                write!(f, "waiting for the key confirmation")
+            }
+            ProtocolState::AwaitingHandshakeResult => {
+                write!(f, "waiting for the handshake result")
+            }
+            ProtocolState::Established => write!(f, "exchanging data"),
+        }
+    }
+}
+
+/// Send a packet
+///
+/// # Arguments
+/// stream: **&mut W** - The stream to write to<br/>
+/// packet: **&PacketType** - The packet to send
+///
+/// # Returns
+/// **ProtocolResult<()>** - An error if the packet could not be sent
+pub fn send_packet<W: Write>(stream: &mut W, packet: &PacketType) -> ProtocolResult<()> {
+    send_message(stream, packet)
+}
+
+/// Route a packet expected in a state
+///
+/// This function will hand the packet received to the route of the state.
+/// A peer leaving ends any state, and any packet the route gives back is unexpected.
+/// Closes are sealed in the channel, so only the states where the session is established route them.
+///
+/// # Arguments
+/// packet: **PacketType** - The packet received<br/>
+/// state: **ProtocolState** - What the side is waiting for<br/>
+/// route: **F** - The function keeping the variants expected in the state and giving back any other packet
+///
+/// # Returns
+/// **ProtocolResult<T>** - The value the route extracted or an error if the peer left or sent an unexpected packet
+pub fn route_packet<T, F>(packet: PacketType, state: ProtocolState, route: F) -> ProtocolResult<T>
+where
+    F: FnOnce(PacketType) -> Result<T, PacketType>,
+{
+    match packet {
+        PacketType::LEAVE => Err(ProtocolError::PeerClosed),
+        packet => route(packet)
+            .map_err(|packet: PacketType| ProtocolError::UnexpectedPacket(state, packet.name())),
+    }
+}
+
+/// Receive the packet expected in a state
+///
+/// This function will read the next packet and route it, see `route_packet`
+///
+/// # Arguments
+/// stream: **&mut R** - The stream to read from<br/>
+/// state: **ProtocolState** - What the side is waiting for<br/>
+/// route: **F** - The function keeping the variants expected in the state and giving back any other packet
+///
+/// # Returns
+/// **ProtocolResult<T>** - The value the route extracted or an error if the peer left, the connection was lost
+/// or the peer sent an unexpected packet
+pub fn receive_packet<R, T, F>(stream: &mut R, state: ProtocolState, route: F) -> ProtocolResult<T>
+where
+    R: Read,
+    F: FnOnce(PacketType) -> Result<T, PacketType>,
+{
+    route_packet(receive_message(stream)?, state, route)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::protocol::shared::types::{DataRequest, HandshakeValidatedRequest};
+
+    #[test]
+    fn test_dispatch() {
+        let mut wire: Vec<u8> = vec![];
+        send_packet(&mut wire, &PacketType::DATA(DataRequest::new(0, vec![1]))).unwrap();
+        send_packet(&mut wire, &PacketType::PING).unwrap();
+        send_packet(&mut wire, &PacketType::LEAVE).unwrap();
+        let mut stream: Cursor<Vec<u8>> = Cursor::new(wire);
+
+        let request: DataRequest = receive_packet(
+            &mut stream,
+            ProtocolState::Established,
+            |packet| match packet {
+                PacketType::DATA(request) => Ok(request),
+                packet => Err(packet),
+            },
+        )
+        .unwrap();
+        assert_eq!(request.payload(), [1]);
+        let unexpected: ProtocolResult<HandshakeValidatedRequest> = receive_packet(
+            &mut stream,
+            ProtocolState::AwaitingHandshakeResult,
+            |packet| match packet {
+                PacketType::HANDSHAKEVALIDATED(request) => Ok(request),
+                packet => Err(packet),
+            },
+        );
+        assert!(matches!(
+            unexpected,
+            Err(ProtocolError::UnexpectedPacket(
+                ProtocolState::AwaitingHandshakeResult,
+                "PING"
+            ))
+        ));
+        // The server leaving ends the handshake cleanly, unlike the stream ending
+        assert!(matches!(
+            receive_packet(&mut stream, ProtocolState::AwaitingServerHello, Ok),
+            Err(ProtocolError::PeerClosed)
+        ));
+        assert!(matches!(
+            receive_packet(&mut stream, ProtocolState::AwaitingServerHello, Ok),
+            Err(ProtocolError::Disconnected)
+        ));
+    }
+}
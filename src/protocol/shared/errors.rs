@@ -0,0 +1,91 @@
+use super::dispatch::ProtocolState;
+
+/// Errors that can occur in the parts of the protocol shared by the client and the server.
+///
+/// This enum is used to represent the different errors that can occur while moving data on the wire.
+///
+/// # Variants
+/// - **Disconnected** - The connection was lost, the stream ended without the peer closing the session
+/// - **PeerClosed** - The peer closed the session or left
+/// - **FrameTooLarge** - The frame announced by the peer exceeds the maximum frame size
+/// - **InvalidData** - The data received could not be decoded
+/// - **Io** - The underlying stream failed
+/// - **TamperedData** - An encrypted message failed its authentication
+/// - **OutOfOrder** - A message arrived with another sequence number than the expected one, holding both
+/// - **UnsupportedVersion** - The peer only speaks a protocol version that is not supported, holding it
+/// - **NoCommonCipherSuite** - No cipher suite is supported by both sides
+/// - **UnexpectedPacket** - A packet arrived in a state not expecting it, holding the state and the name of the packet
+#[derive(Debug)]
+pub enum ProtocolError {
+    Disconnected,
+    PeerClosed,
+    FrameTooLarge(usize),
+    InvalidData,
+    Io(std::io::Error),
+    TamperedData,
+    OutOfOrder(u64, u64),
+    UnsupportedVersion(u16),
+    NoCommonCipherSuite,
+    UnexpectedPacket(ProtocolState, &'static str),
+}
+
+impl std::fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProtocolError::Disconnected => write!(f, "connection lost"),
+            ProtocolError::PeerClosed => write!(f, "peer closed the session"),
+            ProtocolError::FrameTooLarge(size) => write!(f, "frame of {} bytes is too large", size),
+            ProtocolError::InvalidData => write!(f, "invalid data received"),
+            ProtocolError::Io(err) => write!(f, "i/o error: {}", err),
+            ProtocolError::TamperedData => write!(f, "message failed authentication"),
+            ProtocolError::OutOfOrder(expected, received) => write!(
+                f,
+                "message {} received while expecting message {}",
+                received, expected
+            ),
+            ProtocolError::UnsupportedVersion(version) => {
+                write!(f, "protocol version {} is not supported", version)
+            }
+            ProtocolError::NoCommonCipherSuite => write!(f, "no cipher suite in common"),
+            ProtocolError::UnexpectedPacket(state, packet) => {
+                write!(f, "unexpected {} packet while {}", packet, state)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ProtocolError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ProtocolError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for ProtocolError {
+    fn from(err: std::io::Error) -> Self {
+        match err.kind() {
+            std::io::ErrorKind::UnexpectedEof => ProtocolError::Disconnected,
+            _ => ProtocolError::Io(err),
+        }
+    }
+}
+
+impl From<ProtocolError> for std::io::Error {
+    fn from(err: ProtocolError) -> Self {
+        match err {
+            ProtocolError::Io(err) => err,
+            // Only a sealed close ends a session cleanly, anything else cut it short
+            ProtocolError::Disconnected | ProtocolError::PeerClosed => {
+                std::io::Error::from(std::io::ErrorKind::UnexpectedEof)
+            }
+            err => std::io::Error::new(std::io::ErrorKind::InvalidData, err),
+        }
+    }
+}
+
+/// Result type for the shared parts of the protocol.
+///
+/// This type is used to represent the result of the operations shared by the client and the server.
+pub type ProtocolResult<T> = std::result::Result<T, ProtocolError>;
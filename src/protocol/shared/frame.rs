@@ -0,0 +1,202 @@
+//! Length-prefixed framing used for every message exchanged on the wire
+//!
+//! Each frame is made of a big-endian `u32` holding the payload length followed by the payload itself.
+//! The encoding and the checks live apart from the I/O, so the blocking and the asynchronous transports share them.
+
+use std::io::{Read, Write};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use super::{
+    constant::{FRAME_HEADER_SIZE, MAX_FRAME_SIZE},
+    errors::{ProtocolError, ProtocolResult},
+};
+
+/// Encode a frame
+///
+/// This function will prefix the payload with its length
+///
+/// # Arguments
+/// payload: **&[u8]** - The data to send
+///
+/// # Returns
+/// **ProtocolResult<Vec<u8>>** - The whole frame or an error if the payload is too large
+pub fn encode_frame(payload: &[u8]) -> ProtocolResult<Vec<u8>> {
+    if payload.len() > MAX_FRAME_SIZE {
+        return Err(ProtocolError::FrameTooLarge(payload.len()));
+    }
+    let header: [u8; FRAME_HEADER_SIZE] = (payload.len() as u32).to_be_bytes();
+    let mut frame: Vec<u8> = Vec::with_capacity(FRAME_HEADER_SIZE + payload.len());
+
+    frame.extend_from_slice(&header);
+    frame.extend_from_slice(payload);
+    Ok(frame)
+}
+
+/// Decode the header of a frame
+///
+/// # Arguments
+/// header: **[u8; FRAME_HEADER_SIZE]** - The header received
+///
+/// # Returns
+/// **ProtocolResult<usize>** - The size of the payload following it or an error if the frame announced is too large
+pub fn decode_frame_header(header: [u8; FRAME_HEADER_SIZE]) -> ProtocolResult<usize> {
+    let size: usize = u32::from_be_bytes(header) as usize;
+
+    if size > MAX_FRAME_SIZE {
+        return Err(ProtocolError::FrameTooLarge(size));
+    }
+    Ok(size)
+}
+
+/// Write a frame to the stream
+///
+/// This function will prefix the payload with its length and write the whole frame to the stream
+///
+/// # Arguments
+/// stream: **&mut W** - The stream to write to<br/>
+/// payload: **&[u8]** - The data to send
+///
+/// # Returns
+/// **ProtocolResult<()>** - An error if the payload is too large or the stream failed
+pub fn write_frame<W: Write>(stream: &mut W, payload: &[u8]) -> ProtocolResult<()> {
+    stream.write_all(&encode_frame(payload)?)?;
+    stream.flush()?;
+    Ok(())
+}
+
+/// Read a frame from the stream
+///
+/// This function will block until a whole frame has been received, whatever the way TCP split it
+///
+/// # Arguments
+/// stream: **&mut R** - The stream to read from
+///
+/// # Returns
+/// **ProtocolResult<Vec<u8>>** - The payload of the frame or an error if the peer left or announced a frame too large
+pub fn read_frame<R: Read>(stream: &mut R) -> ProtocolResult<Vec<u8>> {
+    let mut header: [u8; FRAME_HEADER_SIZE] = [0; FRAME_HEADER_SIZE];
+    stream.read_exact(&mut header)?;
+    let mut payload: Vec<u8> = vec![0; decode_frame_header(header)?];
+
+    stream.read_exact(&mut payload)?;
+    Ok(payload)
+}
+
+/// Serialize a message
+///
+/// # Arguments
+/// message: **&T** - The message to serialize
+///
+/// # Returns
+/// **ProtocolResult<Vec<u8>>** - The payload of the frame carrying the message or an error if it can not be serialized
+pub fn encode_message<T: Serialize>(message: &T) -> ProtocolResult<Vec<u8>> {
+    serde_json::to_vec(message).map_err(|_| ProtocolError::InvalidData)
+}
+
+/// Deserialize a message
+///
+/// # Arguments
+/// payload: **&[u8]** - The payload of the frame carrying the message
+///
+/// # Returns
+/// **ProtocolResult<T>** - The message or an error if it can not be decoded
+pub fn decode_message<T: DeserializeOwned>(payload: &[u8]) -> ProtocolResult<T> {
+    serde_json::from_slice(payload).map_err(|_| ProtocolError::InvalidData)
+}
+
+/// Send a message in a frame
+///
+/// This function will serialize the message and send it as a single frame
+///
+/// # Arguments
+/// stream: **&mut W** - The stream to write to<br/>
+/// message: **&T** - The message to send
+///
+/// # Returns
+/// **ProtocolResult<()>** - An error if the message could not be sent
+pub fn send_message<W: Write, T: Serialize>(stream: &mut W, message: &T) -> ProtocolResult<()> {
+    write_frame(stream, &encode_message(message)?)
+}
+
+/// Receive a message from a frame
+///
+/// This function will read a single frame and deserialize its content
+///
+/// # Arguments
+/// stream: **&mut R** - The stream to read from
+///
+/// # Returns
+/// **ProtocolResult<T>** - The message received or an error if it could not be read or decoded
+pub fn receive_message<R: Read, T: DeserializeOwned>(stream: &mut R) -> ProtocolResult<T> {
+    decode_message(&read_frame(stream)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    /// Reader handing out at most one byte per call, like a very fragmented TCP stream
+    struct Trickle(Cursor<Vec<u8>>);
+
+    impl Read for Trickle {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let len = buf.len().min(1);
+            self.0.read(&mut buf[..len])
+        }
+    }
+
+    #[test]
+    fn test_frame_round_trip() {
+        let mut wire: Vec<u8> = vec![];
+        write_frame(&mut wire, b"first").unwrap();
+        write_frame(&mut wire, &[7; 4096]).unwrap();
+        write_frame(&mut wire, b"").unwrap();
+
+        let mut stream = Cursor::new(wire);
+        assert_eq!(read_frame(&mut stream).unwrap(), b"first");
+        assert_eq!(read_frame(&mut stream).unwrap(), vec![7; 4096]);
+        assert_eq!(read_frame(&mut stream).unwrap(), b"");
+        assert!(matches!(
+            read_frame(&mut stream),
+            Err(ProtocolError::Disconnected)
+        ));
+    }
+
+    #[test]
+    fn test_frame_split_reads() {
+        let mut wire: Vec<u8> = vec![];
+        send_message(&mut wire, &vec![1u8, 2, 3]).unwrap();
+
+        let mut stream = Trickle(Cursor::new(wire));
+        let message: Vec<u8> = receive_message(&mut stream).unwrap();
+        assert_eq!(message, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_frame_too_large() {
+        let mut wire: Vec<u8> = ((MAX_FRAME_SIZE + 1) as u32).to_be_bytes().to_vec();
+        wire.extend_from_slice(&[0; 16]);
+        assert!(matches!(
+            read_frame(&mut Cursor::new(wire)),
+            Err(ProtocolError::FrameTooLarge(_))
+        ));
+        assert!(matches!(
+            write_frame(&mut vec![], &vec![0; MAX_FRAME_SIZE + 1]),
+            Err(ProtocolError::FrameTooLarge(_))
+        ));
+    }
+
+    #[test]
+    fn test_frame_truncated() {
+        let mut wire: Vec<u8> = vec![];
+        write_frame(&mut wire, b"truncated").unwrap();
+        wire.truncate(wire.len() - 2);
+        assert!(matches!(
+            read_frame(&mut Cursor::new(wire)),
+            Err(ProtocolError::Disconnected)
+        ));
+    }
+}
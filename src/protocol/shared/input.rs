@@ -0,0 +1,84 @@
+//! Standard input of the user, read on its own thread so a session never waits on it to receive
+
+use std::{
+    io::{self, BufRead},
+    sync::{mpsc::Sender, Arc, Mutex},
+    thread,
+};
+
+/// Event driving the sending side of an established session
+///
+/// # Variants
+/// - **Input** - The user entered a line, holding it with its line ending
+/// - **InputClosed** - The standard input reached its end
+/// - **PeerClosed** - The receiving side stopped, because the peer left or sent something wrong
+#[derive(Debug, Clone, PartialEq)]
+pub enum SessionEvent {
+    Input(String),
+    InputClosed,
+    PeerClosed,
+}
+
+/// Lines entered by the user
+///
+/// A thread reads the standard input and hands every line to each session subscribed.
+///
+/// # Fields
+/// - **subscribers** - The sessions receiving the lines, None once the standard input reached its end
+pub struct UserInput {
+    subscribers: Arc<Mutex<Option<Vec<Sender<SessionEvent>>>>>,
+}
+
+impl UserInput {
+    /// Start reading the standard input
+    ///
+    /// This function will spawn the thread reading the standard input, which runs until its end
+    ///
+    /// # Returns
+    /// **UserInput** - The lines entered by the user
+    pub fn spawn() -> Self {
+        let subscribers: Arc<Mutex<Option<Vec<Sender<SessionEvent>>>>> =
+            Arc::new(Mutex::new(Some(Vec::new())));
+        let reader_subscribers: Arc<Mutex<Option<Vec<Sender<SessionEvent>>>>> =
+            Arc::clone(&subscribers);
+
+        thread::spawn(move || {
+            let mut stdin: io::StdinLock = io::stdin().lock();
+            let mut line: String = String::new();
+
+            while stdin.read_line(&mut line).unwrap_or(0) > 0 {
+                if let Some(subscribers) = reader_subscribers.lock().unwrap().as_mut() {
+                    subscribers.retain(|subscriber| {
+                        subscriber.send(SessionEvent::Input(line.clone())).is_ok()
+                    });
+                }
+                line.clear();
+            }
+            for subscriber in reader_subscribers
+                .lock()
+                .unwrap()
+                .take()
+                .unwrap_or_default()
+            {
+                let _ = subscriber.send(SessionEvent::InputClosed);
+            }
+        });
+        return UserInput { subscribers };
+    }
+
+    /// Subscribe a session to the lines entered
+    ///
+    /// This function will send every line entered from now on to the session.
+    /// A session subscribed once the standard input reached its end is told so right away.
+    ///
+    /// # Arguments
+    /// events: **Sender<SessionEvent>** - The events of the session
+    pub fn subscribe(self: &Self, events: Sender<SessionEvent>) {
+        match self.subscribers.lock().unwrap().as_mut() {
+            Some(subscribers) => subscribers.push(events),
+            None => {
+                let _ = events.send(SessionEvent::InputClosed);
+            }
+        }
+    }
+}
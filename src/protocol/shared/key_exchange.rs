@@ -0,0 +1,101 @@
+//! Ephemeral X25519 key agreement giving each session a fresh master key
+
+use rand::rngs::OsRng;
+use x25519_dalek::{EphemeralSecret, PublicKey as KeyShare, SharedSecret};
+use zeroize::Zeroizing;
+
+use super::{
+    constant::{KEY_SHARE_SIZE, MASTER_KEY_SIZE},
+    errors::{ProtocolError, ProtocolResult},
+};
+
+/// Master key agreed during the handshake, wiped when dropped
+pub type MasterKey = Zeroizing<[u8; MASTER_KEY_SIZE]>;
+
+/// Ephemeral key of one side of the handshake
+///
+/// This struct holds an X25519 secret that only lives for one handshake, so that the master key
+/// can not be recovered from the traffic even if the long-term RSA keys leak later on
+///
+/// # Fields
+/// - **secret** - The X25519 secret, consumed by the agreement<br/>
+/// - **share** - The public value sent to the peer
+pub struct EphemeralKey {
+    secret: EphemeralSecret,
+    share: [u8; KEY_SHARE_SIZE],
+}
+
+impl EphemeralKey {
+    /// Create a new ephemeral key
+    ///
+    /// This function will draw a fresh X25519 secret from the operating system
+    ///
+    /// # Returns
+    /// **EphemeralKey** - The key created
+    pub fn new() -> Self {
+        let secret: EphemeralSecret = EphemeralSecret::random_from_rng(OsRng);
+        let share: KeyShare = KeyShare::from(&secret);
+
+        return EphemeralKey {
+            secret,
+            share: share.to_bytes(),
+        };
+    }
+
+    /// Get the key share
+    ///
+    /// This function will return the public value to send to the peer
+    ///
+    /// # Returns
+    /// **[u8; KEY_SHARE_SIZE]** - The key share
+    pub fn share(self: &Self) -> [u8; KEY_SHARE_SIZE] {
+        self.share
+    }
+
+    /// Agree on the master key
+    ///
+    /// This function will combine the secret with the share of the peer, consuming the secret
+    ///
+    /// # Arguments
+    /// peer_share: **&[u8; KEY_SHARE_SIZE]** - The key share received from the peer
+    ///
+    /// # Returns
+    /// **ProtocolResult<MasterKey>** - The master key or an error if the peer sent a low order point
+    pub fn agree(self: Self, peer_share: &[u8; KEY_SHARE_SIZE]) -> ProtocolResult<MasterKey> {
+        let shared_secret: SharedSecret = self.secret.diffie_hellman(&KeyShare::from(*peer_share));
+
+        if !shared_secret.was_contributory() {
+            return Err(ProtocolError::InvalidData);
+        }
+        Ok(Zeroizing::new(shared_secret.to_bytes()))
+    }
+}
+
+impl Default for EphemeralKey {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_agreement() {
+        let client: EphemeralKey = EphemeralKey::new();
+        let server: EphemeralKey = EphemeralKey::new();
+        let client_share: [u8; KEY_SHARE_SIZE] = client.share();
+        let server_share: [u8; KEY_SHARE_SIZE] = server.share();
+
+        let client_master_key: MasterKey = client.agree(&server_share).unwrap();
+        let server_master_key: MasterKey = server.agree(&client_share).unwrap();
+        assert_eq!(client_master_key, server_master_key);
+
+        // The identity point would force the master key to zero
+        assert!(matches!(
+            EphemeralKey::new().agree(&[0; KEY_SHARE_SIZE]),
+            Err(ProtocolError::InvalidData)
+        ));
+    }
+}
@@ -0,0 +1,209 @@
+//! Key schedule deriving every secret of a session from the master key and the handshake transcript
+//!
+//! The master key is extracted with HKDF-SHA256, salted with the digest of the whole transcript, then expanded
+//! into one traffic key and IV per direction and the key confirmation. A peer that saw a different handshake
+//! derives unrelated keys.
+//!
+//! Each traffic secret can be ratcheted into the next generation when the session is rekeyed. The previous
+//! generation can not be recovered from the next one.
+
+use hkdf::Hkdf;
+use sha2::Sha256;
+use subtle::ConstantTimeEq;
+use zeroize::Zeroizing;
+
+use super::{
+    constant::{
+        CLIENT_TO_SERVER_IV_LABEL, CLIENT_TO_SERVER_KEY_LABEL, KEY_CONFIRMATION_LABEL,
+        KEY_CONFIRMATION_SIZE, KEY_UPDATE_IV_LABEL, KEY_UPDATE_KEY_LABEL, MASTER_KEY_SIZE,
+        NONCE_SIZE, SERVER_TO_CLIENT_IV_LABEL, SERVER_TO_CLIENT_KEY_LABEL, SESSION_KEY_SIZE,
+    },
+    transcript::TRANSCRIPT_HASH_SIZE,
+};
+
+/// Secrets protecting one direction of the data channel
+///
+/// # Fields
+/// - **key** - The ChaCha20-Poly1305 key<br/>
+/// - **iv** - The value combined with the sequence number of each message to build its nonce
+#[derive(Clone)]
+pub struct TrafficSecret {
+    key: Zeroizing<[u8; SESSION_KEY_SIZE]>,
+    iv: Zeroizing<[u8; NONCE_SIZE]>,
+}
+
+impl TrafficSecret {
+    /// Get the traffic key
+    ///
+    /// # Returns
+    /// **&[u8; SESSION_KEY_SIZE]** - The traffic key
+    pub fn key(self: &Self) -> &[u8; SESSION_KEY_SIZE] {
+        &self.key
+    }
+
+    /// Get the traffic IV
+    ///
+    /// # Returns
+    /// **&[u8; NONCE_SIZE]** - The traffic IV
+    pub fn iv(self: &Self) -> &[u8; NONCE_SIZE] {
+        &self.iv
+    }
+
+    /// Derive the next generation
+    ///
+    /// This function will expand the traffic key, used as an HKDF pseudorandom key, into a new key and IV
+    ///
+    /// # Returns
+    /// **TrafficSecret** - The secrets replacing this one once the direction is rekeyed
+    pub fn next(self: &Self) -> TrafficSecret {
+        let hkdf: Hkdf<Sha256> = Hkdf::from_prk(self.key.as_slice())
+            .expect("Traffic keys are valid HKDF pseudorandom keys");
+
+        return TrafficSecret {
+            key: expand(&hkdf, KEY_UPDATE_KEY_LABEL),
+            iv: expand(&hkdf, KEY_UPDATE_IV_LABEL),
+        };
+    }
+}
+
+/// Secrets of a session
+///
+/// This struct holds everything derived from the master key once the handshake transcript is complete
+///
+/// # Fields
+/// - **client_to_server** - The secrets protecting the messages sent by the client<br/>
+/// - **server_to_client** - The secrets protecting the messages sent by the server<br/>
+/// - **key_confirmation** - The value proving the client derived the same secrets as the server
+pub struct KeySchedule {
+    client_to_server: TrafficSecret,
+    server_to_client: TrafficSecret,
+    key_confirmation: Zeroizing<[u8; KEY_CONFIRMATION_SIZE]>,
+}
+
+/// Expand a secret
+///
+/// This function will fill an array with the HKDF output for the given label
+///
+/// # Arguments
+/// hkdf: **&Hkdf<Sha256>** - The HKDF state holding the extracted master key<br/>
+/// label: **&[u8]** - The label of the secret
+///
+/// # Returns
+/// **Zeroizing<[u8; N]>** - The secret expanded
+fn expand<const N: usize>(hkdf: &Hkdf<Sha256>, label: &[u8]) -> Zeroizing<[u8; N]> {
+    let mut secret: Zeroizing<[u8; N]> = Zeroizing::new([0; N]);
+
+    hkdf.expand(label, secret.as_mut_slice())
+        .expect("Key schedule sizes are valid HKDF output lengths");
+    secret
+}
+
+impl KeySchedule {
+    /// Run the key schedule
+    ///
+    /// This function will extract the master key salted with the transcript digest and expand every secret of the session
+    ///
+    /// # Arguments
+    /// master_key: **&[u8; MASTER_KEY_SIZE]** - The master key agreed during the handshake<br/>
+    /// transcript_digest: **&[u8; TRANSCRIPT_HASH_SIZE]** - The digest of the whole handshake transcript
+    ///
+    /// # Returns
+    /// **KeySchedule** - The secrets of the session
+    pub fn new(
+        master_key: &[u8; MASTER_KEY_SIZE],
+        transcript_digest: &[u8; TRANSCRIPT_HASH_SIZE],
+    ) -> Self {
+        let hkdf: Hkdf<Sha256> = Hkdf::new(Some(transcript_digest), master_key);
+
+        return KeySchedule {
+            client_to_server: TrafficSecret {
+                key: expand(&hkdf, CLIENT_TO_SERVER_KEY_LABEL),
+                iv: expand(&hkdf, CLIENT_TO_SERVER_IV_LABEL),
+            },
+            server_to_client: TrafficSecret {
+                key: expand(&hkdf, SERVER_TO_CLIENT_KEY_LABEL),
+                iv: expand(&hkdf, SERVER_TO_CLIENT_IV_LABEL),
+            },
+            key_confirmation: expand(&hkdf, KEY_CONFIRMATION_LABEL),
+        };
+    }
+
+    /// Get the client to server secrets
+    ///
+    /// # Returns
+    /// **&TrafficSecret** - The secrets protecting the messages sent by the client
+    pub fn client_to_server(self: &Self) -> &TrafficSecret {
+        &self.client_to_server
+    }
+
+    /// Get the server to client secrets
+    ///
+    /// # Returns
+    /// **&TrafficSecret** - The secrets protecting the messages sent by the server
+    pub fn server_to_client(self: &Self) -> &TrafficSecret {
+        &self.server_to_client
+    }
+
+    /// Get the key confirmation
+    ///
+    /// # Returns
+    /// **&[u8; KEY_CONFIRMATION_SIZE]** - The value the client sends to prove it derived the same secrets
+    pub fn key_confirmation(self: &Self) -> &[u8; KEY_CONFIRMATION_SIZE] {
+        &self.key_confirmation
+    }
+
+    /// Check a key confirmation
+    ///
+    /// This function will compare the key confirmation received with the expected one in constant time,
+    /// so that the time taken does not tell how many leading bytes are right
+    ///
+    /// # Arguments
+    /// received: **&[u8]** - The key confirmation received from the client
+    ///
+    /// # Returns
+    /// **bool** - True if the client derived the same secrets
+    pub fn confirmation_matches(self: &Self, received: &[u8]) -> bool {
+        received.ct_eq(self.key_confirmation.as_slice()).into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_schedule() {
+        let master_key: [u8; MASTER_KEY_SIZE] = [42; MASTER_KEY_SIZE];
+        let schedule: KeySchedule = KeySchedule::new(&master_key, &[0; TRANSCRIPT_HASH_SIZE]);
+        let other_transcript: KeySchedule =
+            KeySchedule::new(&master_key, &[1; TRANSCRIPT_HASH_SIZE]);
+
+        assert_ne!(
+            schedule.client_to_server().key(),
+            schedule.server_to_client().key()
+        );
+        assert_ne!(
+            schedule.client_to_server().iv(),
+            schedule.server_to_client().iv()
+        );
+        assert_ne!(
+            schedule.client_to_server().key(),
+            other_transcript.client_to_server().key()
+        );
+        assert_ne!(
+            schedule.key_confirmation(),
+            other_transcript.key_confirmation()
+        );
+
+        let mut tampered: [u8; KEY_CONFIRMATION_SIZE] = *schedule.key_confirmation();
+        tampered[KEY_CONFIRMATION_SIZE - 1] ^= 1;
+        assert!(schedule.confirmation_matches(schedule.key_confirmation()));
+        assert!(!schedule.confirmation_matches(&tampered));
+        assert!(!schedule.confirmation_matches(&schedule.key_confirmation()[1..]));
+
+        let next: TrafficSecret = schedule.client_to_server().next();
+        assert_ne!(next.key(), schedule.client_to_server().key());
+        assert_ne!(next.iv(), schedule.client_to_server().iv());
+        assert_eq!(next.key(), schedule.client_to_server().next().key());
+    }
+}
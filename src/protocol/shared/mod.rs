@@ -1,2 +1,15 @@
+pub mod async_dispatch;
+pub mod async_session;
+pub mod channel;
 pub mod constant;
+pub mod conversation;
+pub mod dispatch;
+pub mod errors;
+pub mod frame;
+pub mod input;
+pub mod key_exchange;
+pub mod key_schedule;
+pub mod negotiation;
+pub mod session;
+pub mod transcript;
 pub mod types;
@@ -0,0 +1,170 @@
+//! Negotiation of the protocol version and the cipher suite during the hellos
+//!
+//! The client sends the highest version it speaks and the cipher suites it supports, most preferred first.
+//! The server answers with the version both sides speak and the first of those suites it also supports.
+//! Cipher suites travel as numeric codes so that a peer offering suites it does not know yet can still be understood.
+
+use super::{
+    constant::{MIN_PROTOCOL_VERSION, PROTOCOL_VERSION},
+    errors::{ProtocolError, ProtocolResult},
+};
+
+/// Cipher suite of a session
+///
+/// A suite names the key exchange, the encryption of the data channel and the hash of the key schedule together
+///
+/// # Variants
+/// - **X25519ChaCha20Poly1305Sha256** - X25519 key exchange, ChaCha20-Poly1305 data channel and HKDF-SHA256 key schedule
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CipherSuite {
+    X25519ChaCha20Poly1305Sha256,
+}
+
+/// Cipher suites supported, most preferred first
+pub const SUPPORTED_CIPHER_SUITES: &[CipherSuite] = &[CipherSuite::X25519ChaCha20Poly1305Sha256];
+
+impl CipherSuite {
+    /// Get the code of the suite
+    ///
+    /// # Returns
+    /// **u16** - The value identifying the suite on the wire
+    pub fn code(self: &Self) -> u16 {
+        match self {
+            CipherSuite::X25519ChaCha20Poly1305Sha256 => 0x0001,
+        }
+    }
+
+    /// Find a suite from its code
+    ///
+    /// # Arguments
+    /// code: **u16** - The value identifying the suite on the wire
+    ///
+    /// # Returns
+    /// **Option<CipherSuite>** - The suite, None if it is not supported
+    pub fn from_code(code: u16) -> Option<CipherSuite> {
+        SUPPORTED_CIPHER_SUITES
+            .iter()
+            .find(|suite| suite.code() == code)
+            .copied()
+    }
+}
+
+/// Get the codes of the supported suites
+///
+/// # Returns
+/// **Vec<u16>** - The codes of the suites offered in the client hello, most preferred first
+pub fn offered_cipher_suites() -> Vec<u16> {
+    SUPPORTED_CIPHER_SUITES
+        .iter()
+        .map(CipherSuite::code)
+        .collect()
+}
+
+/// Select the protocol version
+///
+/// This function will pick the highest version spoken by both sides, as the server does
+///
+/// # Arguments
+/// client_version: **u16** - The highest version spoken by the client
+///
+/// # Returns
+/// **ProtocolResult<u16>** - The version of the session or an error if the client only speaks older versions
+pub fn select_version(client_version: u16) -> ProtocolResult<u16> {
+    let version: u16 = client_version.min(PROTOCOL_VERSION);
+
+    if version < MIN_PROTOCOL_VERSION {
+        return Err(ProtocolError::UnsupportedVersion(client_version));
+    }
+    Ok(version)
+}
+
+/// Select the cipher suite
+///
+/// This function will pick the first suite offered by the client that is also supported, as the server does
+///
+/// # Arguments
+/// offered: **&[u16]** - The codes of the suites offered by the client, most preferred first
+///
+/// # Returns
+/// **ProtocolResult<CipherSuite>** - The suite of the session or an error if no suite is supported by both sides
+pub fn select_cipher_suite(offered: &[u16]) -> ProtocolResult<CipherSuite> {
+    offered
+        .iter()
+        .find_map(|code| CipherSuite::from_code(*code))
+        .ok_or(ProtocolError::NoCommonCipherSuite)
+}
+
+/// Check the choices of the server
+///
+/// This function will check the server answered with a version and a suite the client offered
+///
+/// # Arguments
+/// version: **u16** - The version selected by the server<br/>
+/// cipher_suite: **Option<u16>** - The code of the suite selected by the server, None if it found no common suite<br/>
+/// offered: **&[u16]** - The codes of the suites offered by the client
+///
+/// # Returns
+/// **ProtocolResult<CipherSuite>** - The suite of the session or an error if the server could not or did not select one the client offered
+pub fn check_server_choice(
+    version: u16,
+    cipher_suite: Option<u16>,
+    offered: &[u16],
+) -> ProtocolResult<CipherSuite> {
+    if !(MIN_PROTOCOL_VERSION..=PROTOCOL_VERSION).contains(&version) {
+        return Err(ProtocolError::UnsupportedVersion(version));
+    }
+    let code: u16 = cipher_suite.ok_or(ProtocolError::NoCommonCipherSuite)?;
+
+    if !offered.contains(&code) {
+        return Err(ProtocolError::InvalidData);
+    }
+    CipherSuite::from_code(code).ok_or(ProtocolError::InvalidData)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_negotiation() {
+        let suite: CipherSuite = CipherSuite::X25519ChaCha20Poly1305Sha256;
+
+        assert_eq!(
+            select_version(PROTOCOL_VERSION + 1).unwrap(),
+            PROTOCOL_VERSION
+        );
+        assert!(matches!(
+            select_version(MIN_PROTOCOL_VERSION - 1),
+            Err(ProtocolError::UnsupportedVersion(_))
+        ));
+        // Unknown suites are skipped, the preference of the client wins
+        assert_eq!(select_cipher_suite(&[0xfff0, suite.code()]).unwrap(), suite);
+        assert!(matches!(
+            select_cipher_suite(&[0xfff0]),
+            Err(ProtocolError::NoCommonCipherSuite)
+        ));
+        assert!(matches!(
+            select_cipher_suite(&[]),
+            Err(ProtocolError::NoCommonCipherSuite)
+        ));
+
+        let offered: Vec<u16> = offered_cipher_suites();
+        assert_eq!(
+            check_server_choice(PROTOCOL_VERSION, Some(suite.code()), &offered).unwrap(),
+            suite
+        );
+        assert!(matches!(
+            check_server_choice(PROTOCOL_VERSION + 1, Some(suite.code()), &offered),
+            Err(ProtocolError::UnsupportedVersion(_))
+        ));
+        assert!(matches!(
+            check_server_choice(PROTOCOL_VERSION, None, &offered),
+            Err(ProtocolError::NoCommonCipherSuite)
+        ));
+        // The server can not pick a suite the client did not offer
+        assert!(matches!(
+            check_server_choice(PROTOCOL_VERSION, Some(suite.code()), &[]),
+            Err(ProtocolError::InvalidData)
+        ));
+    }
+}
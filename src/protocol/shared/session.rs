@@ -0,0 +1,300 @@
+//! Established session over a blocking stream
+//!
+//! The session reads and writes like the stream it runs over: what is written goes through the encrypted channel
+//! in messages of at most `MAX_MESSAGE_SIZE` bytes, and what is read comes out of the messages of the peer in order.
+//! The end of the stream is only read once the peer sealed its close, a session cut short fails with `UnexpectedEof`.
+
+use std::io::{self, Read, Write};
+
+use super::{
+    channel::{ChannelReceiver, ChannelSender, RekeyPolicy, SecureChannel},
+    constant::MAX_MESSAGE_SIZE,
+    dispatch::{route_packet, send_packet, ProtocolState},
+    errors::ProtocolResult,
+    frame::receive_message,
+    types::{DataRequest, PacketType},
+};
+
+/// Packet of an established session, once opened
+///
+/// # Variants
+/// - **Message** - A message of the peer, holding it
+/// - **Closed** - The peer sealed the close of the session
+/// - **Control** - A rekey or a ping, carrying nothing for the application
+#[derive(Debug, PartialEq)]
+pub enum Incoming {
+    Message(Vec<u8>),
+    Closed,
+    Control,
+}
+
+/// Seal a message
+///
+/// This function will renew the sending keys first if the rekey policy says so, then seal the message
+///
+/// # Arguments
+/// sender: **&mut ChannelSender** - The half of the channel used to encrypt the message<br/>
+/// message: **&[u8]** - The message to seal
+///
+/// # Returns
+/// **Vec<PacketType>** - The packets to send, in order
+pub fn seal_message(sender: &mut ChannelSender, message: &[u8]) -> Vec<PacketType> {
+    let mut packets: Vec<PacketType> = Vec::new();
+
+    if sender.needs_rekey() {
+        packets.push(PacketType::REKEY(sender.rekey()));
+    }
+    let request: DataRequest = sender.seal(message);
+    packets.push(PacketType::DATA(request));
+    packets
+}
+
+/// Open a packet of an established session
+///
+/// This function will open the message the packet carries, switch the receiving keys for a rekey or check the close of the peer.
+/// Only a sealed close ends the session, a peer leaving cuts it short.
+///
+/// # Arguments
+/// receiver: **&mut ChannelReceiver** - The half of the channel used to decrypt the packet<br/>
+/// packet: **PacketType** - The packet received
+///
+/// # Returns
+/// **ProtocolResult<Incoming>** - What the packet carried, or an error if the peer left or sent something wrong
+pub fn open_packet(receiver: &mut ChannelReceiver, packet: PacketType) -> ProtocolResult<Incoming> {
+    let packet: PacketType =
+        route_packet(packet, ProtocolState::Established, |packet| match packet {
+            PacketType::DATA(_)
+            | PacketType::REKEY(_)
+            | PacketType::CLOSE(_)
+            | PacketType::PING => Ok(packet),
+            packet => Err(packet),
+        })?;
+
+    match packet {
+        PacketType::DATA(request) => receiver.open(&request).map(Incoming::Message),
+        PacketType::REKEY(request) => {
+            receiver.accept_rekey(&request)?;
+            Ok(Incoming::Control)
+        }
+        PacketType::CLOSE(request) => {
+            receiver.accept_close(&request)?;
+            Ok(Incoming::Closed)
+        }
+        _ => Ok(Incoming::Control),
+    }
+}
+
+/// Send a message through the channel
+///
+/// # Arguments
+/// stream: **&mut W** - The stream to the peer<br/>
+/// sender: **&mut ChannelSender** - The half of the channel used to encrypt the message<br/>
+/// message: **&[u8]** - The message to send
+///
+/// # Returns
+/// **ProtocolResult<()>** - An error if the peer can not be reached
+pub fn send_data<W: Write>(
+    stream: &mut W,
+    sender: &mut ChannelSender,
+    message: &[u8],
+) -> ProtocolResult<()> {
+    for packet in seal_message(sender, message) {
+        send_packet(stream, &packet)?;
+    }
+    Ok(())
+}
+
+/// Receive a message from the channel
+///
+/// This function will wait for the next message of the peer, going through the rekeys and pings coming before it
+///
+/// # Arguments
+/// stream: **&mut R** - The stream to the peer<br/>
+/// receiver: **&mut ChannelReceiver** - The half of the channel used to decrypt the message
+///
+/// # Returns
+/// **ProtocolResult<Option<Vec<u8>>>** - The message, None once the peer sealed the close of the session,
+/// or an error if it sent something wrong or the session was cut short
+pub fn receive_data<R: Read>(
+    stream: &mut R,
+    receiver: &mut ChannelReceiver,
+) -> ProtocolResult<Option<Vec<u8>>> {
+    loop {
+        match open_packet(receiver, receive_message(stream)?)? {
+            Incoming::Message(message) => return Ok(Some(message)),
+            Incoming::Closed => return Ok(None),
+            Incoming::Control => continue,
+        }
+    }
+}
+
+/// Established session over a blocking stream
+///
+/// # Fields
+/// - **stream** - The stream to the peer<br/>
+/// - **sender** - The half of the channel encrypting the messages sent<br/>
+/// - **receiver** - The half of the channel decrypting the messages received<br/>
+/// - **pending** - The part of the last message received not read yet<br/>
+/// - **closed** - Whether the peer closed the session
+pub struct Session<S> {
+    stream: S,
+    sender: ChannelSender,
+    receiver: ChannelReceiver,
+    pending: io::Cursor<Vec<u8>>,
+    closed: bool,
+}
+
+impl<S: Read + Write> Session<S> {
+    /// Create a new session
+    ///
+    /// # Arguments
+    /// stream: **S** - The stream the handshake ran over<br/>
+    /// channel: **SecureChannel** - The channel keyed by the handshake<br/>
+    /// rekey_policy: **RekeyPolicy** - When the sending keys are renewed
+    ///
+    /// # Returns
+    /// **Session<S>** - The session created
+    pub fn new(stream: S, channel: SecureChannel, rekey_policy: RekeyPolicy) -> Self {
+        let (mut sender, receiver): (ChannelSender, ChannelReceiver) = channel.split();
+
+        sender.set_rekey_policy(rekey_policy);
+        return Session {
+            stream,
+            sender,
+            receiver,
+            pending: io::Cursor::new(Vec::new()),
+            closed: false,
+        };
+    }
+
+    /// Get the stream
+    ///
+    /// # Returns
+    /// **&S** - The stream the session runs over
+    pub fn get_ref(self: &Self) -> &S {
+        &self.stream
+    }
+
+    /// Close the session
+    ///
+    /// This function will seal the close of the session for the peer, which then reads the end of the stream
+    ///
+    /// # Returns
+    /// **io::Result<()>** - An error if the peer can not be reached
+    pub fn close(mut self: Self) -> io::Result<()> {
+        send_packet(&mut self.stream, &PacketType::CLOSE(self.sender.close()))?;
+        Ok(())
+    }
+}
+
+impl<S: Read + Write> Read for Session<S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pending.position() as usize == self.pending.get_ref().len() {
+            if self.closed || buf.is_empty() {
+                return Ok(0);
+            }
+            match receive_data(&mut self.stream, &mut self.receiver)? {
+                Some(message) => self.pending = io::Cursor::new(message),
+                None => self.closed = true,
+            }
+        }
+        self.pending.read(buf)
+    }
+}
+
+impl<S: Read + Write> Write for Session<S> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let message: &[u8] = &buf[..buf.len().min(MAX_MESSAGE_SIZE)];
+
+        if message.is_empty() {
+            return Ok(0);
+        }
+        send_data(&mut self.stream, &mut self.sender, message)?;
+        Ok(message.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::{os::unix::net::UnixStream, thread, time::Duration};
+
+    use super::*;
+    use crate::{
+        keys_generator::{
+            keys::{PrivateKey, PublicKey},
+            test_keys::OPENSSL_PKCS8_PRIVATE,
+        },
+        protocol::{
+            client::{known_hosts::ServerTrust, tunnel::Client},
+            server::tunnel::Server,
+        },
+    };
+
+    #[test]
+    fn test_session_read_write() {
+        let private_key: PrivateKey = PrivateKey::from_pem(OPENSSL_PKCS8_PRIVATE).unwrap();
+        let public_key: PublicKey = private_key.public_key().unwrap();
+        let mut client: Client = Client::new(ServerTrust::Pinned(public_key.clone()));
+        let mut server: Server = Server::new((public_key, private_key));
+        let (client_stream, server_stream): (UnixStream, UnixStream) = UnixStream::pair().unwrap();
+        // Larger than a message, so the data is split and the keys renewed along the way
+        let data: Vec<u8> = (0..3 * MAX_MESSAGE_SIZE + 10).map(|i| i as u8).collect();
+
+        client.set_rekey_policy(RekeyPolicy::new(1, Duration::from_secs(3600)));
+        server.set_rekey_policy(RekeyPolicy::new(1, Duration::from_secs(3600)));
+        thread::scope(|scope| {
+            scope.spawn(|| {
+                let mut session: Session<UnixStream> = server.accept(server_stream).unwrap();
+                let mut received: Vec<u8> = vec![0; data.len()];
+
+                session.read_exact(&mut received).unwrap();
+                assert_eq!(received, data);
+                session.write_all(b"Hello client").unwrap();
+                received.clear();
+                assert_eq!(session.read_to_end(&mut received).unwrap(), 0);
+            });
+            let mut session: Session<UnixStream> = client.connect(client_stream).unwrap();
+            let mut reply: [u8; 12] = [0; 12];
+
+            session.write_all(&data).unwrap();
+            session.read_exact(&mut reply).unwrap();
+            assert_eq!(&reply, b"Hello client");
+            session.close().unwrap();
+        });
+    }
+
+    #[test]
+    fn test_session_truncated() {
+        let private_key: PrivateKey = PrivateKey::from_pem(OPENSSL_PKCS8_PRIVATE).unwrap();
+        let public_key: PublicKey = private_key.public_key().unwrap();
+        let mut client: Client = Client::new(ServerTrust::Pinned(public_key.clone()));
+        let server: Server = Server::new((public_key, private_key));
+        let (client_stream, server_stream): (UnixStream, UnixStream) = UnixStream::pair().unwrap();
+
+        thread::scope(|scope| {
+            scope.spawn(|| {
+                let mut session: Session<UnixStream> = server.accept(server_stream).unwrap();
+                let mut received: [u8; 7] = [0; 7];
+
+                session.read_exact(&mut received).unwrap();
+                assert_eq!(&received, b"partial");
+                // Neither a forged leave nor the stream ending can pass for the end of the session
+                for _ in 0..2 {
+                    assert_eq!(
+                        session.read(&mut received).unwrap_err().kind(),
+                        io::ErrorKind::UnexpectedEof
+                    );
+                }
+            });
+            let mut session: Session<UnixStream> = client.connect(client_stream).unwrap();
+
+            session.write_all(b"partial").unwrap();
+            send_packet(&mut session.get_ref(), &PacketType::LEAVE).unwrap();
+            drop(session);
+        });
+    }
+}
@@ -0,0 +1,105 @@
+//! Running hash of the values exchanged during the handshake
+
+use sha2::{Digest, Sha256};
+
+use crate::keys_generator::keys::PublicKey;
+
+use super::types::PacketType;
+
+/// Size of the digest of a transcript
+pub const TRANSCRIPT_HASH_SIZE: usize = 32;
+
+/// Transcript of the handshake
+///
+/// This struct is used to hash, in order, every packet exchanged during the handshake and the other values
+/// both peers must agree on
+///
+/// # Fields
+/// - **hasher** - The SHA-256 state of the values added so far
+#[derive(Clone)]
+pub struct Transcript {
+    hasher: Sha256,
+}
+
+impl Transcript {
+    /// Create a new transcript
+    ///
+    /// This function will create an empty transcript
+    ///
+    /// # Returns
+    /// **Transcript** - The transcript created
+    pub fn new() -> Self {
+        return Transcript {
+            hasher: Sha256::new(),
+        };
+    }
+
+    /// Add a value to the transcript
+    ///
+    /// This function will add the value prefixed by its length so that two different sequences can not hash the same
+    ///
+    /// # Arguments
+    /// data: **&[u8]** - The value to add
+    pub fn update(self: &mut Self, data: &[u8]) {
+        self.hasher.update((data.len() as u32).to_be_bytes());
+        self.hasher.update(data);
+    }
+
+    /// Add a public key to the transcript
+    ///
+    /// This function will add the encryption value and the modulus of the key
+    ///
+    /// # Arguments
+    /// key: **&PublicKey** - The key to add
+    pub fn update_key(self: &mut Self, key: &PublicKey) {
+        self.update(&key.encryption_value().to_bytes_be());
+        self.update(&key.modulus().to_bytes_be());
+    }
+
+    /// Add a packet to the transcript
+    ///
+    /// This function will add the serialized packet, so that both peers hash exactly what was exchanged
+    ///
+    /// # Arguments
+    /// packet: **&PacketType** - The packet sent or received
+    pub fn update_packet(self: &mut Self, packet: &PacketType) {
+        self.update(&serde_json::to_vec(packet).expect("Packets can always be serialized"));
+    }
+
+    /// Get the digest of the transcript
+    ///
+    /// This function will return the hash of every value added so far without consuming the transcript
+    ///
+    /// # Returns
+    /// **[u8; TRANSCRIPT_HASH_SIZE]** - The digest of the transcript
+    pub fn digest(self: &Self) -> [u8; TRANSCRIPT_HASH_SIZE] {
+        self.hasher.clone().finalize().into()
+    }
+}
+
+impl Default for Transcript {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transcript_is_unambiguous() {
+        let mut first: Transcript = Transcript::new();
+        let mut second: Transcript = Transcript::new();
+
+        first.update(b"ab");
+        first.update(b"c");
+        second.update(b"a");
+        second.update(b"bc");
+        assert_ne!(first.digest(), second.digest());
+        second = first.clone();
+        assert_eq!(first.digest(), second.digest());
+        first.update(b"");
+        assert_ne!(first.digest(), second.digest());
+    }
+}
@@ -1,17 +1,22 @@
 use serde::{Deserialize, Serialize};
 
-
-use super::constant::{CLIENT_MASTER_KEY_SIZE, SERVER_MASTER_KEY_SIZE};
+use super::constant::{HELLO_RANDOM_SIZE, KEY_SHARE_SIZE};
 
 /// The hello client request
 ///
 /// This struct is used to represent the hello client request
 ///
 /// # Fields
-/// - **key** - The key sent by the client
+/// - **version** - The highest protocol version spoken by the client<br/>
+/// - **random** - The random bytes of the client<br/>
+/// - **cipher_suites** - The codes of the cipher suites supported by the client, most preferred first<br/>
+/// - **key_share** - The ephemeral X25519 public key of the client
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct HelloClientRequest {
-    key: [u8; CLIENT_MASTER_KEY_SIZE],
+    version: u16,
+    random: [u8; HELLO_RANDOM_SIZE],
+    cipher_suites: Vec<u16>,
+    key_share: [u8; KEY_SHARE_SIZE],
 }
 
 impl HelloClientRequest {
@@ -20,22 +25,55 @@ impl HelloClientRequest {
     /// This function will create a new hello client request
     ///
     /// # Arguments
-    /// key: **[u8; CLIENT_MASTER_KEY_SIZE]** - The key sent by the client
+    /// version: **u16** - The highest protocol version spoken by the client<br/>
+    /// random: **[u8; HELLO_RANDOM_SIZE]** - The random bytes of the client<br/>
+    /// cipher_suites: **Vec<u16>** - The codes of the cipher suites supported by the client, most preferred first<br/>
+    /// key_share: **[u8; KEY_SHARE_SIZE]** - The ephemeral X25519 public key of the client
     ///
     /// # Returns
     /// **HelloClientRequest** - The hello client request created
-    pub fn new(key: [u8; CLIENT_MASTER_KEY_SIZE]) -> Self {
-        return Self { key };
+    pub fn new(
+        version: u16,
+        random: [u8; HELLO_RANDOM_SIZE],
+        cipher_suites: Vec<u16>,
+        key_share: [u8; KEY_SHARE_SIZE],
+    ) -> Self {
+        return Self {
+            version,
+            random,
+            cipher_suites,
+            key_share,
+        };
     }
 
-    /// Get the key
+    /// Get the protocol version
     ///
-    /// This function will return the key sent by the client
+    /// This function will return the highest protocol version spoken by the client
     ///
     /// # Returns
-    /// **[u8; CLIENT_MASTER_KEY_SIZE]** - The key sent by the client
-    pub fn key(self: &Self) -> [u8; CLIENT_MASTER_KEY_SIZE] {
-        self.key.clone()
+    /// **u16** - The highest protocol version spoken by the client
+    pub fn version(self: &Self) -> u16 {
+        self.version
+    }
+
+    /// Get the cipher suites
+    ///
+    /// This function will return the codes of the cipher suites supported by the client
+    ///
+    /// # Returns
+    /// **&[u16]** - The codes of the cipher suites, most preferred first
+    pub fn cipher_suites(self: &Self) -> &[u16] {
+        &self.cipher_suites
+    }
+
+    /// Get the key share
+    ///
+    /// This function will return the ephemeral X25519 public key of the client
+    ///
+    /// # Returns
+    /// **[u8; KEY_SHARE_SIZE]** - The ephemeral X25519 public key of the client
+    pub fn key_share(self: &Self) -> [u8; KEY_SHARE_SIZE] {
+        self.key_share
     }
 }
 
@@ -44,10 +82,16 @@ impl HelloClientRequest {
 /// This struct is used to represent the hello server request
 ///
 /// # Fields
-/// - **key** - The key sent by the server
+/// - **version** - The protocol version selected by the server<br/>
+/// - **random** - The random bytes of the server<br/>
+/// - **cipher_suite** - The code of the cipher suite selected by the server, None if no suite is supported by both sides<br/>
+/// - **key_share** - The ephemeral X25519 public key of the server
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct HelloServerRequest {
-    key: [u8; SERVER_MASTER_KEY_SIZE],
+    version: u16,
+    random: [u8; HELLO_RANDOM_SIZE],
+    cipher_suite: Option<u16>,
+    key_share: [u8; KEY_SHARE_SIZE],
 }
 
 impl HelloServerRequest {
@@ -56,94 +100,159 @@ impl HelloServerRequest {
     /// This function will create a new hello server request
     ///
     /// # Arguments
-    /// key: **[u8; SERVER_MASTER_KEY_SIZE]** - The key sent by the server
+    /// version: **u16** - The protocol version selected by the server<br/>
+    /// random: **[u8; HELLO_RANDOM_SIZE]** - The random bytes of the server<br/>
+    /// cipher_suite: **Option<u16>** - The code of the cipher suite selected by the server, None if no suite is supported by both sides<br/>
+    /// key_share: **[u8; KEY_SHARE_SIZE]** - The ephemeral X25519 public key of the server
     ///
     /// # Returns
     /// **HelloServerRequest** - The hello server request created
-    pub fn new(key: [u8; SERVER_MASTER_KEY_SIZE]) -> Self {
-        return Self { key };
+    pub fn new(
+        version: u16,
+        random: [u8; HELLO_RANDOM_SIZE],
+        cipher_suite: Option<u16>,
+        key_share: [u8; KEY_SHARE_SIZE],
+    ) -> Self {
+        return Self {
+            version,
+            random,
+            cipher_suite,
+            key_share,
+        };
     }
 
-    /// Get the key
+    /// Get the protocol version
     ///
-    /// This function will return the key sent by the server
+    /// This function will return the protocol version selected by the server
     ///
     /// # Returns
-    /// **[u8; SERVER_MASTER_KEY_SIZE]** - The key sent by the server
-    pub fn key(self: &Self) -> [u8; SERVER_MASTER_KEY_SIZE] {
-        self.key.clone()
+    /// **u16** - The protocol version of the session
+    pub fn version(self: &Self) -> u16 {
+        self.version
+    }
+
+    /// Get the cipher suite
+    ///
+    /// This function will return the code of the cipher suite selected by the server
+    ///
+    /// # Returns
+    /// **Option<u16>** - The code of the cipher suite, None if no suite is supported by both sides
+    pub fn cipher_suite(self: &Self) -> Option<u16> {
+        self.cipher_suite
+    }
+
+    /// Get the key share
+    ///
+    /// This function will return the ephemeral X25519 public key of the server
+    ///
+    /// # Returns
+    /// **[u8; KEY_SHARE_SIZE]** - The ephemeral X25519 public key of the server
+    pub fn key_share(self: &Self) -> [u8; KEY_SHARE_SIZE] {
+        self.key_share
     }
 }
 
-/// The sharing public key request
+/// The server signature request
 ///
-/// This struct is used to represent the sharing public key request
+/// This struct is used to represent the proof of identity sent by the server
 ///
 /// # Fields
-/// - **pub_key** - The public key of the client
+/// - **identity_key** - The long-term public key of the server<br/>
+/// - **signature** - The signature of the handshake transcript made with the identity key
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
-pub struct SharingPubKeyRequest {
-    pub_key: (Vec<u8>, Vec<u8>),
+pub struct ServerSignatureRequest {
+    identity_key: (Vec<u8>, Vec<u8>),
+    signature: Vec<u8>,
 }
 
-impl SharingPubKeyRequest {
-    /// Create a new sharing public key request
+impl ServerSignatureRequest {
+    /// Create a new server signature request
     ///
-    /// This function will create a new sharing public key request
+    /// This function will create a new server signature request
     ///
     /// # Arguments
-    /// pub_key: **PublicKey** - The public key of the client
+    /// identity_key: **(Vec<u8>, Vec<u8>)** - The long-term public key of the server<br/>
+    /// signature: **Vec<u8>** - The signature of the handshake transcript
     ///
     /// # Returns
-    /// **SharingPubKeyRequest** - The sharing public key request created
-    pub fn new(pub_key: (Vec<u8>, Vec<u8>)) -> Self {
-        return Self { pub_key };
+    /// **ServerSignatureRequest** - The server signature request created
+    pub fn new(identity_key: (Vec<u8>, Vec<u8>), signature: Vec<u8>) -> Self {
+        return Self {
+            identity_key,
+            signature,
+        };
     }
 
-    /// Get the public key
+    /// Get the identity key
     ///
-    /// This function will return the public key of the client
+    /// This function will return the long-term public key of the server
     ///
     /// # Returns
-    /// **PublicKey** - The public key of the client
-    pub fn pub_key(self: &Self) -> (Vec<u8>, Vec<u8>) {
-        self.pub_key.clone()
+    /// **(Vec<u8>, Vec<u8>)** - The long-term public key of the server
+    pub fn identity_key(self: &Self) -> (Vec<u8>, Vec<u8>) {
+        self.identity_key.clone()
+    }
+
+    /// Get the signature
+    ///
+    /// This function will return the signature of the handshake transcript
+    ///
+    /// # Returns
+    /// **Vec<u8>** - The signature of the handshake transcript
+    pub fn signature(self: &Self) -> Vec<u8> {
+        self.signature.clone()
     }
 }
 
-/// The sharing crypted public key request
+/// The client signature request
 ///
-/// This struct is used to represent the sharing crypted public key request
+/// This struct is used to represent the proof of identity sent by the client
 ///
 /// # Fields
-/// - **crypted_pub_key** - The crypted public key of the server
+/// - **identity_key** - The long-term public key of the client, None if the client has no identity<br/>
+/// - **signature** - The signature of the handshake transcript made with the identity key, empty if the client has no identity
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
-pub struct SharingCryptedPubKeyRequest {
-    crypted_pub_key: Vec<u8>,
+pub struct ClientSignatureRequest {
+    identity_key: Option<(Vec<u8>, Vec<u8>)>,
+    signature: Vec<u8>,
 }
 
-impl SharingCryptedPubKeyRequest {
-    /// Create a new sharing crypted public key request
+impl ClientSignatureRequest {
+    /// Create a new client signature request
     ///
-    /// This function will create a new sharing crypted public key request
+    /// This function will create a new client signature request
     ///
     /// # Arguments
-    /// crypted_pub_key: **Vec<u8>** - The crypted public key of the server
+    /// identity_key: **Option<(Vec<u8>, Vec<u8>)>** - The long-term public key of the client<br/>
+    /// signature: **Vec<u8>** - The signature of the handshake transcript
     ///
     /// # Returns
-    /// **SharingCryptedPubKeyRequest** - The sharing crypted public key request created
-    pub fn new(crypted_pub_key: Vec<u8>) -> Self {
-        return Self { crypted_pub_key };
+    /// **ClientSignatureRequest** - The client signature request created
+    pub fn new(identity_key: Option<(Vec<u8>, Vec<u8>)>, signature: Vec<u8>) -> Self {
+        return Self {
+            identity_key,
+            signature,
+        };
     }
 
-    /// Get the crypted public key
+    /// Get the identity key
     ///
-    /// This function will return the crypted public key of the server
+    /// This function will return the long-term public key of the client
     ///
     /// # Returns
-    /// **Vec<u8>** - The crypted public key of the server
-    pub fn crypted_pub_key(self: &Self) -> Vec<u8> {
-        self.crypted_pub_key.clone()
+    /// **Option<(Vec<u8>, Vec<u8>)>** - The long-term public key of the client, None if the client has no identity
+    pub fn identity_key(self: &Self) -> Option<(Vec<u8>, Vec<u8>)> {
+        self.identity_key.clone()
+    }
+
+    /// Get the signature
+    ///
+    /// This function will return the signature of the handshake transcript
+    ///
+    /// # Returns
+    /// **Vec<u8>** - The signature of the handshake transcript
+    pub fn signature(self: &Self) -> Vec<u8> {
+        self.signature.clone()
     }
 }
 
@@ -152,7 +261,7 @@ impl SharingCryptedPubKeyRequest {
 /// This struct is used to represent the keys validated request
 ///
 /// # Fields
-/// - **key** - The keys validated
+/// - **key** - The key confirmation computed by the client
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct KeysValidatedRequest {
     key: Vec<u8>,
@@ -164,7 +273,7 @@ impl KeysValidatedRequest {
     /// This function will create a new keys validated request
     ///
     /// # Arguments
-    /// key: **Vec<u8>** - The keys validated
+    /// key: **Vec<u8>** - The key confirmation computed by the client
     ///
     /// # Returns
     /// **KeysValidatedRequest** - The keys validated request created
@@ -174,10 +283,10 @@ impl KeysValidatedRequest {
 
     /// Get the keys
     ///
-    /// This function will return the keys validated
+    /// This function will return the key confirmation computed by the client
     ///
     /// # Returns
-    /// **Vec<u8>** - The keys validated
+    /// **Vec<u8>** - The key confirmation
     pub fn key(self: &Self) -> Vec<u8> {
         self.key.clone()
     }
@@ -215,7 +324,56 @@ impl HandshakeValidatedRequest {
     /// # Returns
     /// **[u8; 2]** - The status of the handshake (KO/OK)
     pub fn status(self: &Self) -> [u8; 2] {
-        self.status.clone()
+        self.status
+    }
+}
+
+/// The data request
+///
+/// This struct is used to represent a message sent on the encrypted data channel
+///
+/// # Fields
+/// - **sequence** - The number of messages sent before this one in the same direction<br/>
+/// - **payload** - The encrypted message and its authentication tag
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct DataRequest {
+    sequence: u64,
+    payload: Vec<u8>,
+}
+
+impl DataRequest {
+    /// Create a new data request
+    ///
+    /// This function will create a new data request
+    ///
+    /// # Arguments
+    /// sequence: **u64** - The number of messages sent before this one in the same direction<br/>
+    /// payload: **Vec<u8>** - The encrypted message and its authentication tag
+    ///
+    /// # Returns
+    /// **DataRequest** - The data request created
+    pub fn new(sequence: u64, payload: Vec<u8>) -> Self {
+        return Self { sequence, payload };
+    }
+
+    /// Get the sequence number
+    ///
+    /// This function will return the number of messages sent before this one in the same direction
+    ///
+    /// # Returns
+    /// **u64** - The sequence number of the message
+    pub fn sequence(self: &Self) -> u64 {
+        self.sequence
+    }
+
+    /// Get the payload
+    ///
+    /// This function will return the encrypted message and its authentication tag
+    ///
+    /// # Returns
+    /// **&[u8]** - The encrypted message and its authentication tag
+    pub fn payload(self: &Self) -> &[u8] {
+        &self.payload
     }
 }
 
@@ -226,18 +384,50 @@ impl HandshakeValidatedRequest {
 /// # Variants
 /// - **HELLOCLIENT** - The client is saying hello
 /// - **HELLOSERVER** - The server is saying hello
-/// - **SHARINGPUBKEY** - The client is sharing its public key
-/// - **SHARINGCRYPTEDPUBKEY** - The server is sharing its crypted public key
+/// - **SERVERSIGNATURE** - The server is proving its identity
+/// - **CLIENTSIGNATURE** - The client is proving its identity
 /// - **KEYSVALIDATED** - The keys have been validated
 /// - **HANDSHAKEVALIDATED** - The handshake has been validated or the hanshake failed
-/// - **LEAVE** - The server will close the connection
+/// - **DATA** - An encrypted message of the data channel
+/// - **REKEY** - The sender switches to the next generation of its traffic keys after this message
+/// - **PING** - The sender has nothing to say but keeps the session alive
+/// - **CLOSE** - The sender ends the session, sealed as the last message of its direction
+/// - **LEAVE** - The server will close the connection, without a channel to seal it
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub enum PacketType {
     HELLOCLIENT(HelloClientRequest),
     HELLOSERVER(HelloServerRequest),
-    SHARINGPUBKEY(SharingPubKeyRequest),
-    SHARINGCRYPTEDPUBKEY(SharingCryptedPubKeyRequest),
+    SERVERSIGNATURE(ServerSignatureRequest),
+    CLIENTSIGNATURE(ClientSignatureRequest),
     KEYSVALIDATED(KeysValidatedRequest),
     HANDSHAKEVALIDATED(HandshakeValidatedRequest),
+    DATA(DataRequest),
+    REKEY(DataRequest),
+    PING,
+    CLOSE(DataRequest),
     LEAVE,
 }
+
+impl PacketType {
+    /// Get the name of the packet
+    ///
+    /// This function will return the name of the variant, used to report unexpected packets
+    ///
+    /// # Returns
+    /// **&'static str** - The name of the packet
+    pub fn name(self: &Self) -> &'static str {
+        match self {
+            PacketType::HELLOCLIENT(_) => "HELLOCLIENT",
+            PacketType::HELLOSERVER(_) => "HELLOSERVER",
+            PacketType::SERVERSIGNATURE(_) => "SERVERSIGNATURE",
+            PacketType::CLIENTSIGNATURE(_) => "CLIENTSIGNATURE",
+            PacketType::KEYSVALIDATED(_) => "KEYSVALIDATED",
+            PacketType::HANDSHAKEVALIDATED(_) => "HANDSHAKEVALIDATED",
+            PacketType::DATA(_) => "DATA",
+            PacketType::REKEY(_) => "REKEY",
+            PacketType::PING => "PING",
+            PacketType::CLOSE(_) => "CLOSE",
+            PacketType::LEAVE => "LEAVE",
+        }
+    }
+}